@@ -1,14 +1,21 @@
 
 use criterion::{criterion_group, criterion_main, Criterion};
-use markov_decision::examples::grid_world::{
-    GridWorld,
-    Movements
+use markov_decision::examples::grid_world::GridWorld;
+use markov_decision::markov_decision_process::{
+    BackupScheme,
+    MarkovDecisionProcess,
+    SolverConfig,
+    TabularMdp
 };
-use markov_decision::markov_decision_process::MarkovDecisionProcess;
 
 fn criterion_benchmark(c: &mut Criterion) {
     let grid_world = GridWorld::default();
     grid_world.print_world();
+    let mut tabular = MarkovDecisionProcess::new(
+        TabularMdp::from_state_space(&grid_world),
+        0,
+        0.9
+    );
     let mut mdp = MarkovDecisionProcess::new(
         grid_world,
         0,
@@ -19,9 +26,55 @@ fn criterion_benchmark(c: &mut Criterion) {
         mdp.value_iteration(0.01)
     ));
 
+    c.bench_function("value iteration (tabular)", |b| b.iter(|| 
+        tabular.value_iteration(0.01)
+    ));
+
+    c.bench_function("parallel value iteration", |b| b.iter(|| 
+        mdp.par_value_iteration(0.01)
+    ));
+
     c.bench_function("policy iteration", |b| b.iter(|| 
         mdp.policy_iteration(0.01)
     ));
+
+    c.bench_function("modified policy iteration", |b| b.iter(|| 
+        mdp.modified_policy_iteration(0.01, 5)
+    ));
+
+    c.bench_function("exact policy iteration", |b| b.iter(|| 
+        mdp.exact_policy_iteration()
+    ));
+
+    c.bench_function("linear program", |b| b.iter(|| 
+        mdp.linear_program()
+    ));
+
+    c.bench_function("parallel policy iteration", |b| b.iter(|| 
+        mdp.par_policy_iteration(0.01)
+    ));
+
+    let mut group = c.benchmark_group("backup schemes");
+    group.sample_size(10);
+    for size in [20, 40, 100, 200] {
+        let mut large = MarkovDecisionProcess::new(
+            GridWorld::with_size(size, size),
+            0,
+            0.99
+        );
+        for backup in [
+            BackupScheme::JACOBI,
+            BackupScheme::GAUSS_SEIDEL,
+            BackupScheme::RANDOM_ORDER(42),
+            BackupScheme::PRIORITIZED
+        ] {
+            large.set_solver_config(SolverConfig { backup, ..SolverConfig::default() });
+            group.bench_function(format!("{:?} {}x{}", backup, size, size), |b| b.iter(||
+                large.value_iteration(0.01)
+            ));
+        }
+    }
+    group.finish();
 }
 
 
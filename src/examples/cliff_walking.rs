@@ -0,0 +1,86 @@
+//! The cliff walking grid from Sutton & Barto (Example 6.6). The agent starts
+//! at the bottom left corner and has to reach the bottom right corner. Every
+//! step costs 1, and stepping into the cliff between the two costs 100 and
+//! sends the agent back to the start. Moves are deterministic and use the same
+//! action encoding as GridWorld, see Movements.
+
+use crate::markov_decision_process::{
+    Action,
+    State,
+    StateSpace
+};
+
+pub struct CliffWalking {
+    width: usize,
+    height: usize,
+    all_states: Vec<State>,
+}
+
+impl Default for CliffWalking {
+    fn default() -> Self {
+        CliffWalking::new(12, 4)
+    }
+}
+
+impl CliffWalking {
+    pub fn new(width:usize, height:usize) -> Self {
+        CliffWalking {
+            width,
+            height,
+            all_states: (0..width * height).collect()
+        }
+    }
+
+    pub fn start(&self) -> State {
+        (self.height - 1) * self.width
+    }
+
+    pub fn goal(&self) -> State {
+        self.height * self.width - 1
+    }
+
+    fn is_cliff(&self, s:&State) -> bool {
+        (s / self.width == self.height - 1) && (*s != self.start()) && (*s != self.goal())
+    }
+}
+
+impl StateSpace for CliffWalking {
+
+    fn get_actions_at_state(&self, s:&State) -> Vec<Action> {
+        if self.is_terminal_state(s) {
+            Vec::new()
+        } else {
+            vec![1,2,3,4]
+        }
+    }
+
+    fn get_future_rewards(&self, s:&State, a:&Action) -> Vec<(State, f64, f64)> {
+        let (x, y) = (s % self.width, s / self.width);
+        let (nx, ny) = match a {
+            1 => (x, y.saturating_sub(1)),
+            2 => (x.saturating_sub(1), y),
+            3 => (x, (y + 1).min(self.height - 1)),
+            4 => ((x + 1).min(self.width - 1), y),
+            _ => return Vec::new()
+        };
+        let next:State = ny * self.width + nx;
+        if self.is_cliff(&next) {
+            vec![(self.start(), 1.0, -100.)]
+        } else {
+            vec![(next, 1.0, -1.)]
+        }
+    }
+
+    fn get_all_states(&self) -> std::slice::Iter<'_, State> {
+        self.all_states.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    fn is_terminal_state(&self, s:&State) -> bool {
+        // cliff cells are never entered
+        (*s == self.goal()) | self.is_cliff(s)
+    }
+}
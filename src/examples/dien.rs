@@ -1,13 +1,13 @@
-/// Details about the DieN game, see the markdown.
-/// The key to realize is that if expected earning is positive,
-/// keep playing. If not, your only action should be to QUIT the game.
-/// If we use (current amount of money) as state, then this
-/// translates to after we have certain amount of money, we always
-/// quit. This means that we have a finite state space. This upper 
-/// max is computed as upper_bound in the new function, and the state
-/// at index upper_bound is used as a terminal state.
-/// Values then repsents the expected earning if you start with that much
-/// money.
+//! Details about the DieN game, see the markdown.
+//! The key to realize is that if expected earning is positive,
+//! keep playing. If not, your only action should be to QUIT the game.
+//! If we use (current amount of money) as state, then this
+//! translates to after we have certain amount of money, we always
+//! quit. This means that we have a finite state space. This upper 
+//! max is computed as upper_bound in the new function, and the state
+//! at index upper_bound is used as a terminal state.
+//! Values then repsents the expected earning if you start with that much
+//! money.
 
 use crate::markov_decision_process::{
     Action,
@@ -44,9 +44,9 @@ impl DieN {
         let upper_bound = ((rewards_sum as f64/n as f64)/bad_prob).ceil() as usize;
 
         DieN {
-            n: n,
-            bad_prob: bad_prob,
-            good_numbers: good_numbers,
+            n,
+            bad_prob,
+            good_numbers,
             stop_at: upper_bound,
             states: (0..=upper_bound).collect::<Vec<State>>()
         }
@@ -56,7 +56,7 @@ impl DieN {
 impl StateSpace for DieN {
     fn get_actions_at_state(&self, s:&State) -> Vec<Action> {
         if self.is_terminal_state(s) {
-            vec![0]
+            Vec::new()
         } else {
             vec![0,1] // 0: QUIT, 1: ROLL
         }
@@ -82,7 +82,7 @@ impl StateSpace for DieN {
     }
 
     fn len(&self) -> usize {
-        self.n
+        self.states.len()
     }
 
     fn is_terminal_state(&self, s:&State) -> bool {
@@ -1,11 +1,12 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display};
 use crate::markov_decision_process::{
     Action,
+    GenericStateSpace,
     State,
     StateSpace
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Movements {
     UP,
     LEFT,
@@ -14,15 +15,16 @@ pub enum Movements {
     NOTHING
 }
 
-impl ToString for Movements {
-    fn to_string(&self) -> String {
-        match self {
-            Movements::NOTHING => "-".to_owned(),
-            Movements::UP => "↑".to_owned(),
-            Movements::DOWN => "↓".to_owned(),
-            Movements::LEFT => "←".to_owned(),
-            Movements::RIGHT => "→".to_owned(),
-        }
+impl Display for Movements {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arrow = match self {
+            Movements::NOTHING => "-",
+            Movements::UP => "↑",
+            Movements::DOWN => "↓",
+            Movements::LEFT => "←",
+            Movements::RIGHT => "→",
+        };
+        write!(f, "{}", arrow)
     }
 }
 
@@ -36,6 +38,16 @@ impl Movements {
             _ => Movements::NOTHING,
         }
     }
+
+    pub fn to_usize(&self) -> usize {
+        match self {
+            Movements::UP => 1,
+            Movements::LEFT => 2,
+            Movements::DOWN => 3,
+            Movements::RIGHT => 4,
+            Movements::NOTHING => 0,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -52,15 +64,7 @@ pub struct GridWorld {
 impl Default for GridWorld {
     // default is the one illustrated in the lecture
     fn default() -> Self {
-        GridWorld { 
-            width: 4,
-            height: 3, 
-            unreachable: vec![(1,1)], 
-            terminal: vec![(3,0), (3,1)], 
-            default_reward: -0.04, 
-            special_reward: HashMap::from_iter([((3,0), 1.0), ((3,1), -1.0)]),
-            all_states: (0..12).collect(),
-        }
+        GridWorld::with_size(4, 3)
     }
 }
 
@@ -118,8 +122,62 @@ impl StateSpace for GridWorld {
     }
 }
 
+// The same world with (x, y) coordinates as states and Movements as actions,
+// solve it with MarkovDecisionProcess::from_generic.
+impl GenericStateSpace for GridWorld {
+    type State = (usize, usize);
+    type Action = Movements;
+
+    fn get_actions_at_state(&self, s:&(usize, usize)) -> Vec<Movements> {
+        StateSpace::get_actions_at_state(self, &self.get_idx_from_coord(s.0, s.1))
+            .into_iter()
+            .map(Movements::from_usize)
+            .collect()
+    }
+
+    fn get_future_rewards(&self, s:&(usize, usize), a:&Movements) -> Vec<((usize, usize), f64, f64)> {
+        StateSpace::get_future_rewards(self, &self.get_idx_from_coord(s.0, s.1), &a.to_usize())
+            .into_iter()
+            .map(|(next, p, r)| (self.get_coord_from_idx(&next), p, r))
+            .collect()
+    }
+
+    fn get_all_states(&self) -> Vec<(usize, usize)> {
+        self.all_states.iter().map(|s| self.get_coord_from_idx(s)).collect()
+    }
+
+    fn is_terminal_state(&self, s:&(usize, usize)) -> bool {
+        StateSpace::is_terminal_state(self, &self.get_idx_from_coord(s.0, s.1))
+    }
+}
+
 impl GridWorld {
 
+    /// Every state with a special reward is terminal, entering it pays that reward.
+    pub fn new(width:usize, height:usize, unreachable:Vec<(usize, usize)>, special_reward:Vec<((usize, usize), f64)>, default_reward:f64) -> Self {
+        GridWorld {
+            width,
+            height,
+            unreachable,
+            terminal: special_reward.iter().map(|(coord, _)| *coord).collect(),
+            default_reward,
+            special_reward: HashMap::from_iter(special_reward),
+            all_states: (0..width * height).collect(),
+        }
+    }
+
+    /// The lecture's layout on a larger grid: +1 in the top right corner, -1 right
+    /// below it and a wall at (1, 1). Needs at least 3 x 2 cells.
+    pub fn with_size(width:usize, height:usize) -> Self {
+        GridWorld::new(
+            width,
+            height,
+            vec![(1,1)],
+            vec![((width - 1, 0), 1.0), ((width - 1, 1), -1.0)],
+            -0.04
+        )
+    }
+
     fn move_up(&self, x:usize, y:usize) -> (State, f64) {
         let mut next = if y > 0 {
             (x, y-1)
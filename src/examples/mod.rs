@@ -1,2 +1,3 @@
 pub mod grid_world;
-pub mod dien;
\ No newline at end of file
+pub mod dien;
+pub mod cliff_walking;
\ No newline at end of file
@@ -1,12 +1,7 @@
 pub mod markov_decision_process;
+pub mod reinforcement_learning;
 pub mod examples;
 
-use examples::grid_world::{
-    GridWorld,
-    Movements
-};
-use markov_decision_process::MarkovDecisionProcess;
-
 
 // use std::fmt::Debug;
 // use rayon::prelude::*;
@@ -73,47 +68,6 @@ use markov_decision_process::MarkovDecisionProcess;
 //         }).collect()
 //     }
 
-//     pub fn par_value_iteration(&self, epislon:f64) -> Vec<Policy> {
-//         //Parallel version
-//         let n:usize = self.state_space.get_state_count();
-//         let states:&Vec<State> = self.state_space.get_all_states();
-//         let mut v:Vec<f64> = vec![0.; n];
-//         loop {
-//             let mut next_v:Vec<f64> = Vec::with_capacity(n);
-//             // Compute next v first, in parallel
-//             states.into_par_iter().map(|s:&State| {
-//                 if self.state_space.is_terminal_state(*s) {
-//                     0.
-//                 } else {
-//                     self.state_space.get_actions_at_state(*s).iter()
-//                         .fold(f64::MIN, |acc:f64, action:&Action| 
-//                             acc.max(self.q(&v, *s, *action))
-//                         )
-//                 }
-//             }).collect_into_vec(&mut next_v);
-//             // Find max diff (L1 norm), in parallel
-//             let max_diff:f64 = next_v.par_iter().zip(&v).fold(|| 0.,
-//                 |diff:f64, (new, old):(&f64,&f64)| diff.max((old-new).abs())
-//             ).max_by(|x,y| x.total_cmp(y)).unwrap();
-            
-//             // update v
-//             v = next_v;
-//             if max_diff < epislon {
-//                 break
-//             }
-//         }
-//         // Given optimal v, create optimal policy in parallel.
-//         let mut optimal_policy:Vec<Policy> = Vec::with_capacity(n);
-//         self.state_space.get_all_states().par_iter().map(|s:&State|{
-//             if self.state_space.is_terminal_state(*s) {
-//                 self.default_action
-//             } else {
-//                 self.better_action(&v, *s).1
-//             }
-//         }).collect_into_vec(&mut optimal_policy);
-//         optimal_policy
-//     }
-
 //     pub fn policy_iteration(&self, epislon:f64) -> Vec<Policy> {
 //         // let mapping_prep = mdp.state_space.get_all_states();
 //         let state_count:usize = self.state_space.get_state_count();
@@ -157,50 +111,6 @@ use markov_decision_process::MarkovDecisionProcess;
 //         }
 //     }
 
-//     pub fn par_policy_iteration(&self, epislon:f64) -> Vec<Policy> {
-//         let state_count:usize = self.state_space.get_state_count();
-//         let mut pi:Vec<Policy> = vec![self.default_action; state_count];
-//         let mut v:Vec<f64> = vec![0.; state_count];
-
-//         loop {
-//             loop {
-//                 let mut next_v:Vec<f64> = Vec::with_capacity(state_count);
-//                 // Compute next v first, in parallel
-//                 (0..state_count).into_par_iter().map(|i:usize| {
-//                     let s:State = self.state_space.get_state_from_idx(i);
-//                     if self.state_space.is_terminal_state(s) {
-//                         0.
-//                     } else {
-//                         self.q(&v, s, pi[i])
-//                     }
-//                 }).collect_into_vec(&mut next_v);
-                
-//                 let max_diff:f64 = next_v.par_iter().zip(&v).fold(|| 0.,
-//                     |diff:f64, (new, old):(&f64,&f64)| diff.max((old-new).abs())
-//                 ).max_by(|x,y| x.total_cmp(y)).unwrap();
-
-//                 v = next_v;
-//                 if max_diff < epislon {break}
-//             }
-//             // let stable:Arc<Mutex<bool>> = Arc::new(Mutex::new(true));
-//             let mut new_pi:Vec<Policy> = Vec::with_capacity(state_count);
-//             self.state_space.get_all_states().par_iter()
-//             .map(|s:&State| {
-//                 if self.state_space.is_terminal_state(*s) {
-//                     self.default_action
-//                 } else {
-//                     self.better_action(&v, *s).1
-//                 }
-//             }).collect_into_vec(&mut new_pi);
-
-//             if pi == new_pi {
-//                 break pi
-//             }
-//             pi = new_pi;
-//         }
-
-//     }
-
 //     #[inline]
 //     fn q(&self, current_values:&Vec<f64>, state:State, a:Action) -> f64 {
 
@@ -1,15 +1,15 @@
-mod markov_decision_process;
-mod examples;
-
-use examples::grid_world::{
-    GridWorld,
-    Movements
-};
-use examples::dien::{
+// use markov_decision::examples::grid_world::{
+//     GridWorld,
+//     Movements
+// };
+use markov_decision::examples::dien::{
     DieN
 };
-use markov_decision::markov_decision_process::Policy;
-use markov_decision_process::{MarkovDecisionProcess, MDPSolver};
+use markov_decision::markov_decision_process::{MarkovDecisionProcess, MDPSolver};
+use markov_decision::reinforcement_learning::{
+    environment::Simulator,
+    rollout::rollout
+};
 
 fn main() {
 
@@ -21,21 +21,15 @@ fn main() {
     //     0.9
     // );
 
-    // let mut max_diff = f64::MAX;
-    // let mut iter: u32 = 0;
-    // while max_diff > 0.05 {
-    //     iter += 1;
-    //     max_diff = mdp.update_value();
-    //     let values = mdp.get_learned_values();
-    //     let grid_world = mdp.get_state_space();
-
-    //     println!("\nIteration: {}", iter);
-    //     grid_world.print_on_states(values);
-    //     println!("L1 distance {}, Convergence threshold: {}", max_diff, 0.05);
-    // }
-
-    // let policy1 = mdp.solve(MDPSolver::VALUE_ITER, 0.05);
-    // let policy1 = policy1.into_iter()
+    // let grid_world = GridWorld::default();
+    // let solution1 = mdp.solve_observed(MDPSolver::VALUE_ITER, 0.05, &mut |info:&SweepInfo| {
+    //     println!("\nIteration: {}", info.iteration);
+    //     grid_world.print_on_states(info.values.to_vec());
+    //     println!("L1 distance {}, Convergence threshold: {}", info.residual, 0.05);
+    //     SweepSignal::CONTINUE
+    // }).unwrap();
+
+    // let policy1 = solution1.policy.into_iter()
     //     .map(|x| Movements::from_usize(x)).collect::<Vec<Movements>>();
 
     // let grid_world = mdp.get_state_space();
@@ -49,9 +43,14 @@ fn main() {
         0,
         1.0
     );
-    let policy2 = mdp2.solve(MDPSolver::VALUE_ITER, 0.01);
-    let values2 = mdp2.get_learned_values();
-    println!("DieN best policy: {:?}", policy2);
-    println!("DieN expected winning if start from state: {:?}", values2);
+    let solution2 = mdp2.solve(MDPSolver::VALUE_ITER, 0.01).expect("DieN did not converge.");
+    println!("DieN best policy: {:?}", solution2.policy);
+    println!("DieN expected winning if start from state: {:?}", solution2.values);
+    println!("Converged after {} iterations in {:?}", solution2.iterations, solution2.elapsed);
+
+    // Check the value of state 0 by playing the policy.
+    let mut env = Simulator::new(DieN::new(vec![1,1,1,0,0,0]), vec![0], 1_000, 42);
+    let stats = rollout(&mut env, &solution2.policy, 10_000, 1.0);
+    println!("Simulated winning from state 0: {:.4}, 95% CI {:?}", stats.mean, stats.confidence_interval(1.96));
 
 }
\ No newline at end of file
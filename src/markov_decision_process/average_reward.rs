@@ -0,0 +1,383 @@
+//! Solvers for the average reward (undiscounted, non-episodic) criterion.
+//! The optimal gain g(s) is the long run reward per step when starting at s and
+//! the bias h(s) is the total difference to that rate, so that for the optimal
+//! policy g + h = r + P h. gamma is ignored here. Terminal states (and states
+//! whose action leads nowhere) are treated as absorbing with reward 0.
+
+use ndarray::{Array1, Array2};
+
+use super::{
+    linalg::{self, DENSE_SOLVE_LIMIT},
+    observer::SolverObserver,
+    solution::Sweeps,
+    Action,
+    MarkovDecisionProcess,
+    Policy,
+    Solution,
+    SolveError,
+    State,
+    StateSpace
+};
+
+// Aperiodicity transform used by relative value iteration: each sweep moves
+// only this fraction of the way towards the Bellman backup.
+const TAU: f64 = 0.5;
+// Actions are only switched in policy iteration if they are better by this much.
+const IMPROVEMENT_TOL: f64 = 1e-9;
+/// Largest model in which evaluate_average_reward handles multichain policies. Their
+/// evaluation system has 3 * len() unknowns and is always solved densely.
+pub const AVERAGE_EVALUATION_LIMIT: usize = DENSE_SOLVE_LIMIT / 3;
+
+impl <S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
+
+    fn transitions_under(&self, s:&State, a:&Action) -> Vec<(State, f64, f64)> {
+        let transitions:Vec<(State, f64, f64)> = if self.state_space.is_terminal_state(s) {
+            Vec::new()
+        } else {
+            self.state_space.get_future_rewards(s, a)
+        };
+        if transitions.is_empty() {
+            vec![(*s, 1., 0.)]
+        } else {
+            transitions
+        }
+    }
+
+    pub fn relative_value_iteration(&mut self, epsilon:f64) -> Result<(Solution, Vec<f64>), SolveError> {
+        self.relative_value_iteration_with(epsilon, &mut ())
+    }
+
+    pub(crate) fn relative_value_iteration_with(&mut self, epsilon:f64, observer:&mut dyn SolverObserver) -> Result<(Solution, Vec<f64>), SolveError> {
+        // Relative value iteration for unichain models. Stops once the span of
+        // T(h) - h is below epsilon, which brackets the gain in [min, max].
+        // Relative values are normalised to 0 at the first terminal state, or at the
+        // first state if there are none. Returns the solution with the relative values
+        // and the gain, and sets learned_values to the relative values.
+        self.reset_values();
+        let reference:State = self.state_space.get_all_states()
+            .find(|s| self.state_space.is_terminal_state(s))
+            .or_else(|| self.state_space.get_all_states().next())
+            .copied()
+            .unwrap_or_default();
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        let (gain, converged):(f64, bool) = loop {
+            let (diffs, greedy):(Vec<f64>, Policy) = self.state_space.get_all_states()
+            .map(|s| {
+                if self.state_space.is_terminal_state(s) {
+                    (0., self.default_action)
+                } else {
+                    let (backup, a):(f64, Action) = self.best_action_gamma(1., &self.learned_values, s);
+                    if backup == f64::MIN { (0., a) } else { (backup - self.learned_values[*s], a) }
+                }
+            }).unzip();
+
+            let (lo, hi):(f64, f64) = diffs.iter()
+                .fold((f64::MAX, f64::MIN), |(lo, hi), d| (lo.min(*d), hi.max(*d)));
+            self.learned_values.iter_mut().zip(&diffs).for_each(|(h, d)| *h += TAU * d);
+            let offset:f64 = self.learned_values[reference];
+            self.learned_values.iter_mut().for_each(|h| *h -= offset);
+            let within_limits:bool = sweeps.record(hi - lo, &self.learned_values, &greedy);
+            if hi - lo < epsilon {
+                break ((hi + lo) / 2., true)
+            }
+            if !within_limits {
+                break ((hi + lo) / 2., false)
+            }
+        };
+
+        let pi:Policy = self.state_space.get_all_states()
+        .map(|s| {
+            if self.state_space.is_terminal_state(s) {
+                self.default_action
+            } else {
+                self.best_action_gamma(1., &self.learned_values, s).1
+            }
+        }).collect();
+        let solution:Solution = sweeps.finish(pi, self.learned_values.clone(), f64::INFINITY, converged)?;
+        Ok((solution, vec![gain; self.state_space.len()]))
+    }
+
+    pub fn evaluate_average_reward(&self, pi:&Policy) -> Result<(Vec<f64>, Vec<f64>), SolveError> {
+        // Exact gain and bias of pi. Unichain policies have a constant gain and are
+        // solved with n unknowns, densely or sparsely. Multichain policies need 3n
+        // unknowns, which are only solved densely up to AVERAGE_EVALUATION_LIMIT states.
+        // Returns (gain, bias).
+        let n:usize = self.state_space.len();
+        let mut transitions:Vec<Vec<(State, f64, f64)>> = vec![Vec::new(); n];
+        for s in self.state_space.get_all_states() {
+            transitions[*s] = self.transitions_under(s, &pi[*s]);
+        }
+        match unichain_reference(&transitions) {
+            Some(reference) => evaluate_unichain(&transitions, reference).ok_or(SolveError::Singular),
+            None if n <= AVERAGE_EVALUATION_LIMIT => evaluate_multichain(&transitions).ok_or(SolveError::Singular),
+            None => Err(SolveError::TooLarge { states: n, limit: AVERAGE_EVALUATION_LIMIT }),
+        }
+    }
+
+    pub fn average_reward_policy_iteration(&mut self) -> Result<(Solution, Vec<f64>), SolveError> {
+        self.average_reward_policy_iteration_with(&mut ())
+    }
+
+    pub(crate) fn average_reward_policy_iteration_with(&mut self, observer:&mut dyn SolverObserver) -> Result<(Solution, Vec<f64>), SolveError> {
+        // Multichain policy iteration. Improvement first maximises the expected
+        // next gain, and only among gain-optimal actions the bias backup. An action
+        // is only replaced by a strictly better one. Every evaluation and improvement
+        // counts as one iteration. Returns the solution with the bias and the gain,
+        // and sets learned_values to the bias.
+        let mut pi:Policy = self.initial_policy();
+
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        loop {
+            let (gain, bias) = self.evaluate_average_reward(&pi)?;
+            let within_limits:bool = sweeps.tick(&bias, &pi);
+            let expected_gain = |s:&State, a:&Action| self.transitions_under(s, a)
+                .into_iter()
+                .fold(0., |acc, (next, p, _)| acc + p * gain[next]);
+
+            let mut changed:bool = false;
+            let mut candidates:Vec<Vec<Action>> = vec![Vec::new(); pi.len()];
+            for s in self.state_space.get_all_states() {
+                if self.state_space.is_terminal_state(s) {
+                    continue
+                }
+                let actions:Vec<Action> = self.state_space.get_actions_at_state(s);
+                let scored:Vec<(Action, f64)> = actions.into_iter()
+                    .map(|a| (a, expected_gain(s, &a)))
+                    .collect();
+                let best:f64 = scored.iter().fold(f64::MIN, |acc, (_, g)| acc.max(*g));
+                if expected_gain(s, &pi[*s]) < best - IMPROVEMENT_TOL {
+                    pi[*s] = scored.iter().find(|(_, g)| *g == best).unwrap().0;
+                    changed = true;
+                }
+                candidates[*s] = scored.into_iter()
+                    .filter(|(_, g)| *g >= best - IMPROVEMENT_TOL)
+                    .map(|(a, _)| a)
+                    .collect();
+            }
+
+            if !changed {
+                for s in self.state_space.get_all_states() {
+                    let current:f64 = self.q_gamma(1., &bias, s, &pi[*s]);
+                    let (best_v, best_a):(f64, Action) = candidates[*s].iter()
+                        .fold((f64::MIN, pi[*s]), |acc, a| {
+                            let v:f64 = self.q_gamma(1., &bias, s, a);
+                            if v > acc.0 { (v, *a) } else { acc }
+                        });
+                    if best_v > current + IMPROVEMENT_TOL {
+                        pi[*s] = best_a;
+                        changed = true;
+                    }
+                }
+            }
+
+            if !changed || !within_limits {
+                if !changed {
+                    // the evaluation equations are solved exactly
+                    sweeps.set_residual(0.);
+                }
+                self.learned_values = bias.clone();
+                let solution:Solution = sweeps.finish(pi, bias, f64::INFINITY, !changed)?;
+                return Ok((solution, gain));
+            }
+        }
+    }
+}
+
+/// A state that every state reaches under the given transitions, which exists
+/// exactly when the chain has a single closed class (is unichain).
+fn unichain_reference(transitions:&[Vec<(State, f64, f64)>]) -> Option<State> {
+    let n:usize = transitions.len();
+    let mut predecessors:Vec<Vec<State>> = vec![Vec::new(); n];
+    for (s, row) in transitions.iter().enumerate() {
+        for (next, p, _) in row {
+            if *p > 0. {
+                predecessors[*next].push(s);
+            }
+        }
+    }
+    // The state that finishes last in a depth first search of the reversed chain
+    // lies in a source class of the reversed chain, i.e. in a closed class.
+    let mut visited:Vec<bool> = vec![false; n];
+    let mut last:Option<State> = None;
+    for root in 0..n {
+        if visited[root] {
+            continue
+        }
+        visited[root] = true;
+        let mut stack:Vec<(State, usize)> = vec![(root, 0)];
+        while let Some(&(s, i)) = stack.last() {
+            if i < predecessors[s].len() {
+                stack.last_mut().unwrap().1 += 1;
+                let t:State = predecessors[s][i];
+                if !visited[t] {
+                    visited[t] = true;
+                    stack.push((t, 0));
+                }
+            } else {
+                stack.pop();
+                last = Some(s);
+            }
+        }
+    }
+    // the chain is unichain iff that state is reached from everywhere
+    let reference:State = last?;
+    let mut reached:Vec<bool> = vec![false; n];
+    reached[reference] = true;
+    let mut queue:Vec<State> = vec![reference];
+    while let Some(s) = queue.pop() {
+        for t in &predecessors[s] {
+            if !reached[*t] {
+                reached[*t] = true;
+                queue.push(*t);
+            }
+        }
+    }
+    if reached.iter().all(|r| *r) { Some(reference) } else { None }
+}
+
+fn evaluate_unichain(transitions:&[Vec<(State, f64, f64)>], reference:State) -> Option<(Vec<f64>, Vec<f64>)> {
+    // Solves g + (I - P) h = r with h(reference) = 0, where the gain g takes the
+    // column of h(reference), which is nonsingular for unichain P. The bias is
+    // h - c, where c is the mean of h under the stationary distribution. The same
+    // system with right hand side h gives c + (I - P) w = h, and so c.
+    let n:usize = transitions.len();
+    let mut rows:Vec<Vec<(State, f64)>> = Vec::with_capacity(n);
+    let mut rewards:Array1<f64> = Array1::zeros(n);
+    for (s, row) in transitions.iter().enumerate() {
+        let mut entries:Vec<(State, f64)> = row.iter()
+            .filter(|(next, _, _)| *next != reference)
+            .map(|(next, p, _)| (*next, -p))
+            .collect();
+        if s != reference {
+            entries.push((s, 1.));
+        }
+        entries.push((reference, 1.));
+        rows.push(entries);
+        rewards[s] = row.iter().fold(0., |acc, (_, p, r)| acc + p * r);
+    }
+    let mut h:Array1<f64> = linalg::solve_rows(rows.clone(), rewards, Array1::zeros(n))?;
+    let gain:f64 = h[reference];
+    h[reference] = 0.;
+    let mean:f64 = linalg::solve_rows(rows, h.clone(), Array1::zeros(n))?[reference];
+    Some((vec![gain; n], h.iter().map(|v| v - mean).collect()))
+}
+
+fn evaluate_multichain(transitions:&[Vec<(State, f64, f64)>]) -> Option<(Vec<f64>, Vec<f64>)> {
+    // Gain and bias for any chain structure, by solving
+    //   (I - P) g = 0,  g + (I - P) h = r,  h + (I - P) w = 0
+    // densely. The last block pins h down to the bias, while w itself is not
+    // unique, so the system is singular but consistent.
+    let n:usize = transitions.len();
+    let mut a = Array2::<f64>::zeros((3 * n, 3 * n));
+    let mut b = Array1::<f64>::zeros(3 * n);
+    for (s, row) in transitions.iter().enumerate() {
+        for block in 0..3 {
+            a[[block * n + s, block * n + s]] += 1.;
+            for (next, p, _) in row {
+                a[[block * n + s, block * n + next]] -= p;
+            }
+        }
+        a[[n + s, s]] += 1.;
+        a[[2 * n + s, n + s]] += 1.;
+        b[n + s] = row.iter().fold(0., |acc, (_, p, r)| acc + p * r);
+    }
+    let solution:Vec<f64> = linalg::solve_dense_consistent(a, b)?.to_vec();
+    Some((solution[..n].to_vec(), solution[n..2 * n].to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::grid_world::GridWorld,
+        markov_decision_process::{MDPSolver, TabularMdp}
+    };
+
+    // Service control of a queue of at most n - 1 jobs: a job arrives with probability 0.4
+    // and is served with probability 0.2 (action 0) or 0.5 at a cost of 2 (action 1).
+    // Every waiting job costs 1 per step. Every policy is unichain.
+    fn queue(n:usize) -> TabularMdp {
+        let mut builder = TabularMdp::builder(n);
+        for q in 0..n {
+            for (a, service, cost) in [(0, 0.2, 0.), (1, 0.5, 2.)] {
+                let r:f64 = -(q as f64) - cost;
+                builder.add_transition(q, a, (q + 1).min(n - 1), 0.4, r);
+                builder.add_transition(q, a, q.saturating_sub(1), service, r);
+                builder.add_transition(q, a, q, 1. - 0.4 - service, r);
+            }
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn unichain_and_multichain_evaluation_agree() {
+        let mdp = MarkovDecisionProcess::new(queue(40), 0, 1.);
+        let pi:Policy = (0..40).map(|q| if q < 5 { 0 } else { 1 }).collect();
+        let mut transitions:Vec<Vec<(State, f64, f64)>> = vec![Vec::new(); 40];
+        for s in 0..40 {
+            transitions[s] = mdp.transitions_under(&s, &pi[s]);
+        }
+        let reference:State = unichain_reference(&transitions).unwrap();
+        let (gain, bias) = evaluate_unichain(&transitions, reference).unwrap();
+        let (dense_gain, dense_bias) = evaluate_multichain(&transitions).unwrap();
+        // the 3n system is singular, so its solution is the less accurate one
+        assert!(gain.iter().zip(&dense_gain).all(|(a, b)| (a - b).abs() < 1e-6));
+        assert!(bias.iter().zip(&dense_bias).all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+
+    #[test]
+    fn policy_iteration_solves_large_unichain_models() {
+        // Beyond DENSE_SOLVE_LIMIT, so the evaluations run BiCGSTAB. Every action
+        // spreads over three scattered states, which mixes fast and keeps the
+        // systems well conditioned, unlike a long queue.
+        let n:usize = DENSE_SOLVE_LIMIT + 200;
+        let mut builder = TabularMdp::builder(n);
+        for s in 0..n {
+            for a in 0..2 {
+                for k in 0..3 {
+                    let next:State = (s * 7919 + a * 104_729 + k * 15_485_863) % n;
+                    let r:f64 = ((s * 31 + a * 17 + k) % 10) as f64;
+                    builder.add_transition(s, a, next, 1. / 3., r);
+                }
+            }
+        }
+        let mut mdp = MarkovDecisionProcess::new(builder.build(), 0, 1.);
+        let (solution, gain) = mdp.average_reward_policy_iteration().unwrap();
+        // gain + h(s) = max_a r(s, a) + sum_s' p(s'|s, a) h(s') everywhere
+        let h:&[f64] = &solution.values;
+        for s in 0..n {
+            let (best, _) = mdp.best_action_gamma(1., h, &s);
+            assert!((best - h[s] - gain[s]).abs() < 1e-6, "state {}: {} != {}", s, best - h[s], gain[s]);
+        }
+        assert!(gain.iter().all(|g| (g - gain[0]).abs() < 1e-9));
+    }
+
+    #[test]
+    fn policy_iteration_rejects_large_multichain_models() {
+        let mut mdp = MarkovDecisionProcess::new(GridWorld::with_size(20, 20), 0, 1.);
+        match mdp.solve(MDPSolver::AVERAGE_POLICY_ITER, 0.) {
+            Err(SolveError::TooLarge { states, limit }) => {
+                assert_eq!(states, 400);
+                assert_eq!(limit, AVERAGE_EVALUATION_LIMIT);
+            },
+            other => panic!("expected TooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn policy_iteration_agrees_with_relative_value_iteration() {
+        // Staying at 0 earns 1 per step, going back and forth earns (0 + 3) / 2.
+        let mut builder = TabularMdp::builder(2);
+        builder
+            .add_transition(0, 0, 0, 1., 1.)
+            .add_transition(0, 1, 1, 1., 0.)
+            .add_transition(1, 0, 0, 1., 3.);
+        let mut mdp = MarkovDecisionProcess::new(builder.build(), 0, 1.);
+        let (solution, gain) = mdp.average_reward_policy_iteration().unwrap();
+        assert_eq!(solution.policy, vec![1, 0]);
+        assert!(gain.iter().all(|g| (g - 1.5).abs() < 1e-9));
+        let (solution, relative_gain) = mdp.relative_value_iteration(1e-9).unwrap();
+        assert_eq!(solution.policy, vec![1, 0]);
+        assert!(relative_gain.iter().all(|g| (g - 1.5).abs() < 1e-6));
+    }
+}
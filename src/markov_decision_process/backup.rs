@@ -0,0 +1,161 @@
+//! Alternatives to the Jacobi sweep of value_iteration. Gauss-Seidel and the random
+//! order variant update the values in place, so every backup already sees the new
+//! values of the states updated before it in the same sweep. Prioritized sweeping
+//! always backs up the state with the largest Bellman error next and then only
+//! recomputes the errors of its predecessors.
+
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    solution::Sweeps,
+    Action,
+    MarkovDecisionProcess,
+    Policy,
+    State,
+    StateSpace
+};
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupScheme {
+    #[default]
+    JACOBI, // every backup of a sweep reads the values of the previous sweep
+    GAUSS_SEIDEL, // in place, in the order of get_all_states
+    RANDOM_ORDER(u64), // in place, in a new random order every sweep, seeded
+    // len() backups per sweep, of the state with the largest Bellman error and then
+    // of its predecessors. The stopping rule is applied to the Bellman errors
+    // T(v) - v of all states after each sweep.
+    PRIORITIZED
+}
+
+// Queue entry of prioritized sweeping, the heap pops the largest error first.
+struct Priority(f64, State);
+
+impl PartialEq for Priority {
+    fn eq(&self, other:&Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Priority {}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other:&Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Priority {
+    fn cmp(&self, other:&Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl <S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
+
+    pub(crate) fn in_place_value_iteration(&mut self, epsilon:f64, sweeps:&mut Sweeps, seed:Option<u64>) -> bool {
+        // Gauss-Seidel sweeps, in a shuffled order if a seed is given.
+        let mut order:Vec<State> = self.state_space.get_all_states().copied().collect();
+        let mut rng:Option<StdRng> = seed.map(StdRng::seed_from_u64);
+        let mut greedy:Policy = vec![self.default_action; order.len()];
+        loop {
+            if let Some(rng) = rng.as_mut() {
+                order.shuffle(rng);
+            }
+            let (mut lo, mut hi):(f64, f64) = (0., 0.);
+            for s in &order {
+                if self.state_space.is_terminal_state(s) {
+                    continue
+                }
+                let (v, a):(f64, Action) = self.best_action(&self.learned_values, s);
+                let diff:f64 = v - self.learned_values[*s];
+                self.learned_values[*s] = v; // side effect
+                greedy[*s] = a;
+                lo = lo.min(diff);
+                hi = hi.max(diff);
+            }
+            let within_limits:bool = sweeps.record(hi.max(-lo), &self.learned_values, &greedy);
+            if self.config.stopping.is_met(lo, hi, epsilon, self.gamma) {
+                return true
+            }
+            if !within_limits {
+                return false
+            }
+        }
+    }
+
+    pub(crate) fn prioritized_value_iteration(&mut self, epsilon:f64, sweeps:&mut Sweeps) -> bool {
+        let n:usize = self.state_space.len();
+        // predecessors[s] lists every state with an action that can lead to s
+        let mut predecessors:Vec<Vec<State>> = vec![Vec::new(); n];
+        for s in self.state_space.get_all_states() {
+            if self.state_space.is_terminal_state(s) {
+                continue
+            }
+            for a in self.state_space.get_actions_at_state(s) {
+                for (next, p, _) in self.state_space.get_future_rewards(s, &a) {
+                    if p > 0. {
+                        predecessors[next].push(*s);
+                    }
+                }
+            }
+        }
+        for states in predecessors.iter_mut() {
+            states.sort_unstable();
+            states.dedup();
+        }
+
+        // backups[s] = (T(v)(s), greedy action), kept up to date for every non-terminal state.
+        // Queue entries are stale once the error of their state has changed.
+        let mut backups:Vec<(f64, Action)> = vec![(0., self.default_action); n];
+        let mut queue:BinaryHeap<Priority> = BinaryHeap::new();
+        let error = |backups:&[(f64, Action)], values:&[f64], s:State| (backups[s].0 - values[s]).abs();
+        for s in self.state_space.get_all_states() {
+            if !self.state_space.is_terminal_state(s) {
+                backups[*s] = self.best_action(&self.learned_values, s);
+                queue.push(Priority(error(&backups, &self.learned_values, *s), *s));
+            }
+        }
+
+        let mut greedy:Policy = vec![self.default_action; n];
+        loop {
+            let mut done:usize = 0;
+            while done < n {
+                let s:State = match std::iter::from_fn(|| queue.pop())
+                    .find(|Priority(e, s)| *e == error(&backups, &self.learned_values, *s))
+                {
+                    Some(Priority(e, s)) if e > 0. => s,
+                    _ => break // every Bellman error is 0
+                };
+                self.learned_values[s] = backups[s].0; // side effect
+                // only the backups of s and of its predecessors read v(s)
+                for p in predecessors[s].iter().copied().chain(Some(s)) {
+                    if self.state_space.is_terminal_state(&p) {
+                        continue
+                    }
+                    backups[p] = self.best_action(&self.learned_values, &p);
+                    done += 1;
+                    queue.push(Priority(error(&backups, &self.learned_values, p), p));
+                }
+            }
+
+            let (lo, hi):(f64, f64) = self.state_space.get_all_states()
+            .filter(|s| !self.state_space.is_terminal_state(s))
+            .fold((0., 0.), |(lo, hi), s| {
+                greedy[*s] = backups[*s].1;
+                let diff:f64 = backups[*s].0 - self.learned_values[*s];
+                (lo.min(diff), hi.max(diff))
+            });
+            let within_limits:bool = sweeps.record(hi.max(-lo), &self.learned_values, &greedy);
+            if self.config.stopping.is_met(lo, hi, epsilon, self.gamma) {
+                return true
+            }
+            if !within_limits {
+                return false
+            }
+        }
+    }
+}
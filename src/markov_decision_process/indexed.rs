@@ -0,0 +1,219 @@
+//! State spaces over arbitrary state and action types, e.g. (x, y) coordinates
+//! and enums. The solvers work on dense usize indices, so a GenericStateSpace is
+//! wrapped in an IndexedStateSpace, which enumerates the states and actions once
+//! and translates between the two. Dense usize spaces should keep implementing
+//! StateSpace directly, which skips the translation entirely.
+
+use std::{collections::HashMap, hash::Hash, slice::Iter};
+
+use super::{
+    Action,
+    MarkovDecisionProcess,
+    MDPSolver,
+    Policy,
+    Solution,
+    SolveError,
+    State,
+    StateSpace
+};
+
+pub trait GenericStateSpace {
+    type State: Clone + Eq + Hash;
+    type Action: Clone + Eq + Hash;
+
+    fn get_actions_at_state(&self, s:&Self::State) -> Vec<Self::Action>;
+    // return type: next_state, prob, reward
+    fn get_future_rewards(&self, s:&Self::State, a:&Self::Action) -> Vec<(Self::State, f64, f64)>;
+    // The order of the states here defines their index.
+    fn get_all_states(&self) -> Vec<Self::State>;
+    fn is_terminal_state(&self, s:&Self::State) -> bool;
+}
+
+pub struct IndexedStateSpace<G: GenericStateSpace> {
+    space: G,
+    states: Vec<G::State>,
+    state_index: HashMap<G::State, State>,
+    actions: Vec<G::Action>,
+    action_index: HashMap<G::Action, Action>,
+    indices: Vec<State>,
+}
+
+impl <G: GenericStateSpace> IndexedStateSpace<G> {
+
+    pub fn new(space:G) -> Self {
+        let states:Vec<G::State> = space.get_all_states();
+        let state_index:HashMap<G::State, State> = states.iter().cloned()
+            .enumerate()
+            .map(|(i, s)| (s, i))
+            .collect();
+        // actions are numbered in order of first appearance
+        let mut actions:Vec<G::Action> = Vec::new();
+        let mut action_index:HashMap<G::Action, Action> = HashMap::new();
+        for s in &states {
+            for a in space.get_actions_at_state(s) {
+                if !action_index.contains_key(&a) {
+                    action_index.insert(a.clone(), actions.len());
+                    actions.push(a);
+                }
+            }
+        }
+        IndexedStateSpace {
+            indices: (0..states.len()).collect(),
+            space,
+            states,
+            state_index,
+            actions,
+            action_index,
+        }
+    }
+
+    pub fn get_generic_space(&self) -> &G {
+        &self.space
+    }
+
+    pub fn state(&self, s:&State) -> &G::State {
+        &self.states[*s]
+    }
+
+    pub fn action(&self, a:&Action) -> &G::Action {
+        &self.actions[*a]
+    }
+
+    pub fn index_of_state(&self, s:&G::State) -> Option<State> {
+        self.state_index.get(s).copied()
+    }
+
+    pub fn index_of_action(&self, a:&G::Action) -> Option<Action> {
+        self.action_index.get(a).copied()
+    }
+
+    /// Maps a policy over indices back to actions. Terminal states and states
+    /// without actions are left out.
+    pub fn decode_policy(&self, pi:&Policy) -> HashMap<G::State, G::Action> {
+        self.states.iter().zip(pi)
+            .filter(|(s, _)| !self.space.is_terminal_state(s) && !self.space.get_actions_at_state(s).is_empty())
+            .map(|(s, a)| (s.clone(), self.actions[*a].clone()))
+            .collect()
+    }
+
+    pub fn decode_values(&self, values:&[f64]) -> HashMap<G::State, f64> {
+        self.states.iter().cloned().zip(values.iter().copied()).collect()
+    }
+}
+
+impl <G: GenericStateSpace> StateSpace for IndexedStateSpace<G> {
+
+    fn get_actions_at_state(&self, s:&State) -> Vec<Action> {
+        self.space.get_actions_at_state(&self.states[*s])
+            .iter()
+            .map(|a| self.action_index[a])
+            .collect()
+    }
+
+    fn get_future_rewards(&self, s:&State, a:&Action) -> Vec<(State, f64, f64)> {
+        // Next states missing from get_all_states become the out of range index len(),
+        // so that validate (and with it solve_generic) reports them instead of this
+        // panicking.
+        self.space.get_future_rewards(&self.states[*s], &self.actions[*a])
+            .into_iter()
+            .map(|(next, p, r)| (self.index_of_state(&next).unwrap_or(self.states.len()), p, r))
+            .collect()
+    }
+
+    fn get_all_states(&self) -> Iter<'_, State> {
+        self.indices.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    fn is_terminal_state(&self, s:&State) -> bool {
+        self.space.is_terminal_state(&self.states[*s])
+    }
+}
+
+impl <G> MarkovDecisionProcess<IndexedStateSpace<G>>
+where
+    G: GenericStateSpace + std::marker::Sync,
+    G::State: std::marker::Sync,
+    G::Action: std::marker::Sync
+{
+    /// Builds a MarkovDecisionProcess over a generic state space. The default action
+    /// only fills in the (meaningless) policy entries of terminal states.
+    pub fn from_generic(space:G, gamma:f64) -> Self {
+        MarkovDecisionProcess::new(IndexedStateSpace::new(space), 0, gamma)
+    }
+
+    /// Like solve, with the policy expressed in the generic state and action types.
+    /// The model is validated first, a next state missing from get_all_states would
+    /// otherwise make the solvers index out of range.
+    pub fn solve_generic(&mut self, method:MDPSolver, epsilon:f64) -> Result<HashMap<G::State, G::Action>, SolveError> {
+        self.validate().map_err(SolveError::InvalidModel)?;
+        let solution:Solution = self.solve(method, epsilon)?;
+        Ok(self.state_space.decode_policy(&solution.policy))
+    }
+
+    pub fn get_generic_values(&self) -> HashMap<G::State, f64> {
+        self.state_space.decode_values(&self.learned_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markov_decision_process::ModelError;
+
+    // A walk 0 -> 1 -> 2, where the step from 1 can leave the listed states.
+    struct Walk {
+        leaks: bool,
+    }
+
+    impl GenericStateSpace for Walk {
+        type State = i32;
+        type Action = char;
+
+        fn get_actions_at_state(&self, s:&i32) -> Vec<char> {
+            if *s == 2 { Vec::new() } else { vec!['+'] }
+        }
+
+        fn get_future_rewards(&self, s:&i32, _a:&char) -> Vec<(i32, f64, f64)> {
+            if *s == 1 && self.leaks { vec![(7, 1., 1.)] } else { vec![(s + 1, 1., 1.)] }
+        }
+
+        fn get_all_states(&self) -> Vec<i32> {
+            vec![0, 1, 2]
+        }
+
+        fn is_terminal_state(&self, s:&i32) -> bool {
+            *s == 2
+        }
+    }
+
+    #[test]
+    fn unknown_next_states_are_reported_by_validate() {
+        let mdp = MarkovDecisionProcess::from_generic(Walk { leaks: true }, 0.9);
+        let errors:Vec<ModelError> = mdp.validate().unwrap_err();
+        assert_eq!(errors, vec![ModelError::NextStateOutOfRange { state: 1, action: 0, next: 3 }]);
+    }
+
+    #[test]
+    fn solve_generic_rejects_unknown_next_states() {
+        let mut mdp = MarkovDecisionProcess::from_generic(Walk { leaks: true }, 0.9);
+        match mdp.solve_generic(MDPSolver::VALUE_ITER, 1e-9) {
+            Err(SolveError::InvalidModel(errors)) =>
+                assert_eq!(errors, vec![ModelError::NextStateOutOfRange { state: 1, action: 0, next: 3 }]),
+            other => panic!("expected InvalidModel, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn solve_generic_decodes_the_policy() {
+        let mut mdp = MarkovDecisionProcess::from_generic(Walk { leaks: false }, 0.5);
+        assert!(mdp.validate().is_ok());
+        let policy:HashMap<i32, char> = mdp.solve_generic(MDPSolver::VALUE_ITER, 1e-9).unwrap();
+        assert_eq!(policy, HashMap::from([(0, '+'), (1, '+')]));
+        let values:HashMap<i32, f64> = mdp.get_generic_values();
+        assert!((values[&0] - 1.5).abs() < 1e-9 && (values[&1] - 1.).abs() < 1e-9);
+    }
+}
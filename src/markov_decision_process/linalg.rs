@@ -0,0 +1,282 @@
+//! Small linear algebra helpers used to evaluate a policy exactly, i.e. to solve
+//! (I - gamma * P_pi) v = r_pi. ndarray does not ship a solver without LAPACK,
+//! so a dense Gaussian elimination and a sparse BiCGSTAB are implemented here.
+
+use ndarray::{s, Array1, Array2, Zip};
+
+/// Below this many states the system is solved densely, above it iteratively.
+pub const DENSE_SOLVE_LIMIT: usize = 1000;
+
+/// Solves a x = b by Gaussian elimination with partial pivoting.
+/// Returns None if a is (numerically) singular.
+pub fn solve_dense(mut a: Array2<f64>, mut b: Array1<f64>) -> Option<Array1<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        // pick the row with the largest pivot for numerical stability
+        let pivot = (col..n)
+            .max_by(|i, j| a[[*i, col]].abs().total_cmp(&a[[*j, col]].abs()))?;
+        if a[[pivot, col]].abs() < f64::EPSILON {
+            return None
+        }
+        if pivot != col {
+            for k in col..n {
+                a.swap([pivot, k], [col, k]);
+            }
+            b.swap(pivot, col);
+        }
+        let pivot_row = a.slice(s![col, col..]).to_owned();
+        for row in (col + 1)..n {
+            let factor = a[[row, col]] / pivot_row[0];
+            if factor != 0. {
+                a.slice_mut(s![row, col..]).scaled_add(-factor, &pivot_row);
+                b[row] -= factor * b[col];
+            }
+        }
+    }
+    // back substitution
+    let mut x = Array1::<f64>::zeros(n);
+    for row in (0..n).rev() {
+        let tail = a.slice(s![row, (row + 1)..]).dot(&x.slice(s![(row + 1)..]));
+        x[row] = (b[row] - tail) / a[[row, row]];
+    }
+    Some(x)
+}
+
+/// Finds a solution of a consistent but possibly singular system a x = b by
+/// Gaussian elimination, setting the free variables to 0. Components of x that are
+/// uniquely determined by the system are therefore exact. Returns None if the
+/// system is inconsistent.
+pub fn solve_dense_consistent(mut a: Array2<f64>, mut b: Array1<f64>) -> Option<Array1<f64>> {
+    let (n_rows, n_cols) = a.dim();
+    let tol = 1e-10 * a.iter().fold(1f64, |acc, v| acc.max(v.abs()));
+    let mut pivots: Vec<(usize, usize)> = Vec::new(); // (row, col)
+    let mut row = 0;
+    for col in 0..n_cols {
+        if row == n_rows {
+            break
+        }
+        let pivot = (row..n_rows)
+            .max_by(|i, j| a[[*i, col]].abs().total_cmp(&a[[*j, col]].abs()))?;
+        if a[[pivot, col]].abs() < tol {
+            continue // free variable
+        }
+        if pivot != row {
+            for k in col..n_cols {
+                a.swap([pivot, k], [row, k]);
+            }
+            b.swap(pivot, row);
+        }
+        let pivot_row = a.slice(s![row, col..]).to_owned();
+        for other in (row + 1)..n_rows {
+            let factor = a[[other, col]] / pivot_row[0];
+            if factor != 0. {
+                a.slice_mut(s![other, col..]).scaled_add(-factor, &pivot_row);
+                b[other] -= factor * b[row];
+            }
+        }
+        pivots.push((row, col));
+        row += 1;
+    }
+    let b_tol = 1e-8 * b.iter().fold(1f64, |acc, v| acc.max(v.abs()));
+    if (row..n_rows).any(|i| b[i].abs() > b_tol) {
+        return None
+    }
+    let mut x = Array1::<f64>::zeros(n_cols);
+    for (row, col) in pivots.into_iter().rev() {
+        let tail = a.slice(s![row, (col + 1)..]).dot(&x.slice(s![(col + 1)..]));
+        x[col] = (b[row] - tail) / a[[row, col]];
+    }
+    Some(x)
+}
+
+/// Solves the square system with the given rows, densely up to DENSE_SOLVE_LIMIT
+/// unknowns and with BiCGSTAB started from x0 above. Duplicate entries in a row are
+/// summed. Returns None if the system is singular or BiCGSTAB does not converge.
+pub fn solve_rows(rows: Vec<Vec<(usize, f64)>>, b: Array1<f64>, x0: Array1<f64>) -> Option<Array1<f64>> {
+    let n = b.len();
+    if n <= DENSE_SOLVE_LIMIT {
+        let mut a = Array2::<f64>::zeros((n, n));
+        for (i, row) in rows.into_iter().enumerate() {
+            for (j, v) in row {
+                a[[i, j]] += v;
+            }
+        }
+        solve_dense(a, b)
+    } else {
+        CsrMatrix::from_rows(rows).bicgstab(&b, x0, 1e-12, 10 * n)
+    }
+}
+
+/// Square sparse matrix in compressed sparse row layout.
+pub struct CsrMatrix {
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    data: Vec<f64>,
+}
+
+impl CsrMatrix {
+    /// Builds the matrix row by row. Duplicate column entries in a row are summed by mat_vec.
+    pub fn from_rows<I>(rows: I) -> Self
+    where I: IntoIterator<Item = Vec<(usize, f64)>>
+    {
+        let mut indptr: Vec<usize> = vec![0];
+        let mut indices: Vec<usize> = Vec::new();
+        let mut data: Vec<f64> = Vec::new();
+        for row in rows {
+            for (j, v) in row {
+                indices.push(j);
+                data.push(v);
+            }
+            indptr.push(indices.len());
+        }
+        CsrMatrix { indptr, indices, data }
+    }
+
+    pub fn n_rows(&self) -> usize {
+        self.indptr.len() - 1
+    }
+
+    pub fn mat_vec(&self, x: &Array1<f64>) -> Array1<f64> {
+        Array1::from_iter((0..self.n_rows()).map(|i| {
+            (self.indptr[i]..self.indptr[i + 1])
+                .fold(0., |acc, k| acc + self.data[k] * x[self.indices[k]])
+        }))
+    }
+
+    /// Solves a x = b with BiCGSTAB, starting from x0. The method is restarted
+    /// whenever it breaks down. Returns None if it does not reach `tol` (relative
+    /// residual) within `max_iter` iterations.
+    pub fn bicgstab(&self, b: &Array1<f64>, x0: Array1<f64>, tol: f64, max_iter: usize) -> Option<Array1<f64>> {
+        let b_norm = b.dot(b).sqrt().max(f64::MIN_POSITIVE);
+        let mut x = x0;
+        let mut r = b - &self.mat_vec(&x);
+        let mut r_hat = r.clone();
+        let mut p = r.clone();
+        let mut rho = r_hat.dot(&r);
+        for _ in 0..max_iter {
+            if r.dot(&r).sqrt() / b_norm < tol {
+                return Some(x)
+            }
+            let v = self.mat_vec(&p);
+            let denom = r_hat.dot(&v);
+            if rho.abs() < f64::MIN_POSITIVE || denom.abs() < f64::MIN_POSITIVE {
+                // breakdown, restart with the current residual
+                r_hat = r.clone();
+                p = r.clone();
+                rho = r_hat.dot(&r);
+                continue
+            }
+            let alpha = rho / denom;
+            let h = &x + &(alpha * &p);
+            let s = &r - &(alpha * &v);
+            if s.dot(&s).sqrt() / b_norm < tol {
+                return Some(h)
+            }
+            let t = self.mat_vec(&s);
+            let tt = t.dot(&t);
+            let omega = if tt > 0. { t.dot(&s) / tt } else { 0. };
+            x = &h + &(omega * &s);
+            r = &s - &(omega * &t);
+            if omega.abs() < f64::MIN_POSITIVE {
+                // stagnation, restart with the current residual
+                r_hat = r.clone();
+                p = r.clone();
+                rho = r_hat.dot(&r);
+                continue
+            }
+            let rho_next = r_hat.dot(&r);
+            let beta = (rho_next / rho) * (alpha / omega);
+            Zip::from(&mut p).and(&r).and(&v).for_each(|p, r, v| {
+                *p = r + beta * (*p - omega * v);
+            });
+            rho = rho_next;
+        }
+        if r.dot(&r).sqrt() / b_norm < tol {
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    fn assert_close(x:&Array1<f64>, expected:&[f64], tol:f64) {
+        assert_eq!(x.len(), expected.len());
+        for (a, b) in x.iter().zip(expected) {
+            assert!((a - b).abs() <= tol, "{} != {}", x, Array1::from_vec(expected.to_vec()));
+        }
+    }
+
+    #[test]
+    fn dense_solve_with_pivoting() {
+        // a[0, 0] = 0 needs a row swap
+        let a = array![[0., 2., 1.], [1., -1., 0.], [3., 0., -2.]];
+        let b = array![7., -1., -3.]; // x = (1, 2, 3)
+        assert_close(&solve_dense(a, b).unwrap(), &[1., 2., 3.], 1e-12);
+    }
+
+    #[test]
+    fn dense_solve_rejects_singular() {
+        let a = array![[1., 2.], [2., 4.]];
+        assert!(solve_dense(a, array![1., 2.]).is_none());
+    }
+
+    #[test]
+    fn dense_consistent_solves_rank_deficient_systems() {
+        // x0 + x1 = 3 twice, x2 = 5: x2 is determined, x1 is free and set to 0
+        let a = array![[1., 1., 0.], [2., 2., 0.], [0., 0., 1.]];
+        let x = solve_dense_consistent(a.clone(), array![3., 6., 5.]).unwrap();
+        assert_close(&x, &[3., 0., 5.], 1e-12);
+        assert_close(&a.dot(&x), &[3., 6., 5.], 1e-12);
+        // a square non-singular system has the usual unique solution
+        let a = array![[4., 1.], [2., 3.]];
+        assert_close(&solve_dense_consistent(a, array![6., 8.]).unwrap(), &[1., 2.], 1e-12);
+    }
+
+    #[test]
+    fn dense_consistent_rejects_inconsistent_systems() {
+        let a = array![[1., 1.], [2., 2.]];
+        assert!(solve_dense_consistent(a, array![3., 7.]).is_none());
+    }
+
+    #[test]
+    fn csr_sums_duplicate_entries() {
+        let m = CsrMatrix::from_rows(vec![vec![(0, 1.), (1, 2.), (0, 3.)], vec![(1, -1.)]]);
+        assert_eq!(m.n_rows(), 2);
+        assert_close(&m.mat_vec(&array![1., 10.]), &[24., -10.], 0.);
+    }
+
+    #[test]
+    fn bicgstab_matches_dense_solve() {
+        // nonsymmetric, diagonally dominant tridiagonal system with a known solution
+        let n:usize = 200;
+        let expected:Vec<f64> = (0..n).map(|i| (i as f64 * 0.1).sin()).collect();
+        let rows:Vec<Vec<(usize, f64)>> = (0..n).map(|i| {
+            let mut row:Vec<(usize, f64)> = vec![(i, 4.)];
+            if i > 0 { row.push((i - 1, -1.5)) }
+            if i + 1 < n { row.push((i + 1, -0.5)) }
+            row
+        }).collect();
+        let mut dense = Array2::<f64>::zeros((n, n));
+        for (i, row) in rows.iter().enumerate() {
+            for (j, v) in row {
+                dense[[i, *j]] += v;
+            }
+        }
+        let m = CsrMatrix::from_rows(rows);
+        let b = m.mat_vec(&Array1::from_vec(expected.clone()));
+        let x = m.bicgstab(&b, Array1::zeros(n), 1e-12, 10 * n).unwrap();
+        assert_close(&x, &expected, 1e-9);
+        assert_close(&solve_dense(dense, b).unwrap(), &expected, 1e-9);
+    }
+
+    #[test]
+    fn bicgstab_gives_up_after_max_iter() {
+        let m = CsrMatrix::from_rows((0..50).map(|i| vec![(i, 2. + i as f64)]));
+        assert!(m.bicgstab(&Array1::ones(50), Array1::zeros(50), 1e-12, 0).is_none());
+    }
+}
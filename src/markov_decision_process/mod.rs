@@ -1,21 +1,60 @@
-// use ndarray::{Array1,ArrayView1, parallel::prelude::{IntoParallelRefIterator, IndexedParallelIterator, ParallelIterator}};
+mod average_reward;
+mod backup;
+mod indexed;
+mod linalg;
+mod observer;
+pub mod persistence;
+pub mod simplex;
+mod solution;
+mod stochastic;
+mod tabular;
+mod validation;
 
-use std::{cell::RefCell, slice::Iter};
+pub use average_reward::AVERAGE_EVALUATION_LIMIT;
+pub use backup::BackupScheme;
+pub use indexed::{GenericStateSpace, IndexedStateSpace};
+pub use observer::{SolverObserver, SweepInfo, SweepSignal};
+pub use solution::{Solution, SolveError, SolverConfig, StoppingRule, TieBreak};
+pub use stochastic::StochasticPolicy;
+pub use tabular::{TabularMdp, TabularMdpBuilder};
+pub use validation::{validate, ModelError};
+
+use solution::Sweeps;
+use simplex::LpError;
+use ndarray::{Array1, Array2};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::{
+    IndexedParallelIterator,
+    IntoParallelRefIterator,
+    IntoParallelRefMutIterator,
+    ParallelIterator
+};
+use std::slice::Iter;
 
 pub type Action = usize; // see State.
 pub type State = usize; // 
 pub type Policy = Vec<Action>;
+pub type QTable = Vec<Vec<(Action, f64)>>; // per state, (a, Q(s, a)) for every available action
+pub type PolicySet = Vec<Vec<Action>>; // per state, every action that is (nearly) optimal
+pub type OccupancyMeasure = Vec<(State, Action, f64)>; // (s, a, x(s, a))
 
-#[derive(Debug)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Default)]
 pub enum MDPSolver {
     VALUE_ITER,
-    POLICY_ITER
-}
-
-impl Default for MDPSolver {
-    fn default() -> Self {
-        MDPSolver::POLICY_ITER
-    }
+    PAR_VALUE_ITER,
+    #[default]
+    POLICY_ITER,
+    PAR_POLICY_ITER,
+    MODIFIED_POLICY_ITER(usize), // number of evaluation sweeps per improvement
+    EXACT_POLICY_ITER,
+    LINEAR_PROGRAM,
+    RELATIVE_VALUE_ITER, // average reward criterion, gamma is ignored
+    // Average reward criterion, gamma is ignored. Unichain policies are evaluated
+    // at any size, a multichain policy in a model with more than
+    // AVERAGE_EVALUATION_LIMIT (333) states gives SolveError::TooLarge.
+    AVERAGE_POLICY_ITER,
+    SOFT_VALUE_ITER(f64) // temperature, the policy is the mode of the Boltzmann policy
 }
 
 impl From<&str> for MDPSolver {
@@ -23,6 +62,14 @@ impl From<&str> for MDPSolver {
         match value {
             "policy_iteration" | "policy" => MDPSolver::POLICY_ITER,
             "value_iteration" | "value" => MDPSolver::VALUE_ITER,
+            "par_value_iteration" | "par_value" => MDPSolver::PAR_VALUE_ITER,
+            "par_policy_iteration" | "par_policy" => MDPSolver::PAR_POLICY_ITER,
+            "modified_policy_iteration" | "modified_policy" => MDPSolver::MODIFIED_POLICY_ITER(5),
+            "exact_policy_iteration" | "exact_policy" => MDPSolver::EXACT_POLICY_ITER,
+            "linear_program" | "lp" => MDPSolver::LINEAR_PROGRAM,
+            "relative_value_iteration" | "relative_value" => MDPSolver::RELATIVE_VALUE_ITER,
+            "average_policy_iteration" | "average_policy" => MDPSolver::AVERAGE_POLICY_ITER,
+            "soft_value_iteration" | "soft_value" => MDPSolver::SOFT_VALUE_ITER(1.0),
             _ => MDPSolver::default()
         }
     }
@@ -35,6 +82,9 @@ pub trait StateSpace {
     // Assume that get_all_states returns all states in the right order, state 0 is at index 0 and so on..
     fn get_all_states(&self) -> Iter<'_, State>; // can this be abstracted away as a trait?
     fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     fn is_terminal_state(&self, s:&State) -> bool;
 }
 
@@ -42,32 +92,51 @@ pub struct MarkovDecisionProcess<S: StateSpace + std::marker::Sync> {
     state_space: S, 
     default_action: Action,
     gamma: f64,
-    learned_values:RefCell<Vec<f64>>
+    learned_values:Vec<f64>,
+    config: SolverConfig
 }
 
-impl <'a, S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
+impl <S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
 
     pub fn new(state_space:S, default:Action, gamma:f64) -> MarkovDecisionProcess<S> {
         let values = vec![0.;state_space.len()];
         MarkovDecisionProcess {
-            state_space: state_space, 
+            state_space, 
             default_action:default,
-            gamma: gamma,
-            learned_values: RefCell::new(values)
+            gamma,
+            learned_values: values,
+            config: SolverConfig::default()
         }
     }
 
+    pub fn set_solver_config(&mut self, config:SolverConfig) {
+        self.config = config;
+    }
+
+    pub fn get_solver_config(&self) -> &SolverConfig {
+        &self.config
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<ModelError>> {
+        validate(&self.state_space)
+    }
+
     pub fn reset_values(&mut self) {
-        *self.learned_values.borrow_mut() = vec![0.;self.state_space.len()];
+        self.learned_values = vec![0.;self.state_space.len()];
     }
     
     #[inline]
-    fn best_action(&self, current_values:&Vec<f64>, s:&State) -> (f64, Action) {
+    fn best_action(&self, current_values:&[f64], s:&State) -> (f64, Action) {
+        self.best_action_gamma(self.gamma, current_values, s)
+    }
+
+    #[inline]
+    fn best_action_gamma(&self, gamma:f64, current_values:&[f64], s:&State) -> (f64, Action) {
         //Given v, returns the better action for the current state.
         self.state_space.get_actions_at_state(s)
         .into_iter()
         .fold((f64::MIN, self.default_action), |accum:(f64, Action), a:Action| {
-            let value_given_a:f64 = self.q(current_values, s, &a);
+            let value_given_a:f64 = self.q_gamma(gamma, current_values, s, &a);
             if value_given_a > accum.0 {
                 (value_given_a, a)
             } else { // (value given action, action)
@@ -77,16 +146,114 @@ impl <'a, S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
     }
 
     #[inline]
-    fn q(&self, current_values:&Vec<f64>, state:&State, action:&Action) -> f64 {
+    fn q(&self, current_values:&[f64], state:&State, action:&Action) -> f64 {
+        self.q_gamma(self.gamma, current_values, state, action)
+    }
+
+    #[inline]
+    fn q_gamma(&self, gamma:f64, current_values:&[f64], state:&State, action:&Action) -> f64 {
         // first get all possible (next_state, r) for this 
         self.state_space.get_future_rewards(state, action)
         .into_iter()
         .fold(0., |acc:f64, (next, p, r)| 
-            acc + p * (r + self.gamma * current_values[next])
+            acc + p * (r + gamma * current_values[next])
         )
     }
 
-    pub fn update_value(&self) -> f64 {
+    fn initial_policy(&self) -> Policy {
+        // The default action wherever it is available, else the first available action.
+        self.state_space.get_all_states()
+        .map(|s| {
+            let actions:Vec<Action> = self.state_space.get_actions_at_state(s);
+            if actions.contains(&self.default_action) {
+                self.default_action
+            } else {
+                actions.first().copied().unwrap_or(self.default_action)
+            }
+        }).collect()
+    }
+
+    fn greedy_policy(&self, current_values:&[f64]) -> Policy {
+        self.state_space.get_all_states()
+        .map(|s| {
+            if self.state_space.is_terminal_state(s) {
+                self.default_action
+            } else {
+                self.best_action(current_values, s).1
+            }
+        }).collect::<Vec<Action>>()
+    }
+
+    /// Certified upper bound on max_s v*(s) - v_pi(s), from one backup of any values v
+    /// (MacQueen bounds, with c = gamma / (1 - gamma)):
+    ///   v* <= T(v) + c * max(T(v) - v),  v_pi >= T_pi(v) + c * min(T_pi(v) - v).
+    /// For the greedy policy of v this is c * span(T(v) - v). Terminal states must have
+    /// value 0, states without actions are ignored. Infinite for gamma >= 1.
+    pub fn suboptimality_bound(&self, pi:&Policy, current_values:&[f64]) -> f64 {
+        if self.gamma >= 1. {
+            return f64::INFINITY
+        }
+        // terminal states are fixed points, so both extremes start at 0
+        let (gap, hi, lo):(f64, f64, f64) = self.state_space.get_all_states()
+        .filter(|s| !self.state_space.is_terminal_state(s))
+        .fold((0., 0., 0.), |(gap, hi, lo), s| {
+            let best:f64 = self.best_action(current_values, s).0;
+            if best == f64::MIN {
+                return (gap, hi, lo)
+            }
+            let q_pi:f64 = self.q(current_values, s, &pi[*s]);
+            (gap.max(best - q_pi), hi.max(best - current_values[*s]), lo.min(q_pi - current_values[*s]))
+        });
+        gap + self.gamma / (1. - self.gamma) * (hi - lo)
+    }
+
+    fn break_ties(&self, pi:Policy) -> Policy {
+        // Re-picks the action of every state among its near-optimal actions, see TieBreak.
+        let tie_break:TieBreak = self.config.tie_break;
+        if tie_break == TieBreak::SOLVER {
+            return pi
+        }
+        let mut rng:Option<StdRng> = match tie_break {
+            TieBreak::RANDOM(seed) => Some(StdRng::seed_from_u64(seed)),
+            _ => None
+        };
+        self.get_policy_set(self.config.tie_tolerance)
+        .into_iter()
+        .zip(pi)
+        .map(|(tied, a)| {
+            let lowest:Action = match tied.iter().min() {
+                Some(lowest) => *lowest,
+                None => return a
+            };
+            match tie_break {
+                TieBreak::SOLVER => a,
+                TieBreak::FIRST => tied[0],
+                TieBreak::LOWEST_INDEX => lowest,
+                TieBreak::RANDOM(_) => tied[rng.as_mut().unwrap().gen_range(0..tied.len())],
+                TieBreak::PREFER_DEFAULT => {
+                    if tied.contains(&self.default_action) { self.default_action } else { lowest }
+                }
+            }
+        }).collect()
+    }
+
+    fn finish(&self, sweeps:Sweeps, pi:Policy, converged:bool) -> Result<Solution, SolveError> {
+        let pi:Policy = self.break_ties(pi);
+        let bound:f64 = self.suboptimality_bound(&pi, &self.learned_values);
+        sweeps.finish(pi, self.learned_values.clone(), bound, converged)
+    }
+
+    fn bellman_residual(&self, current_values:&[f64]) -> f64 {
+        // max |T(v) - v| over the states that have actions.
+        self.state_space.get_all_states()
+        .filter(|s| !self.state_space.is_terminal_state(s))
+        .fold(0., |acc:f64, s| {
+            let best:f64 = self.best_action(current_values, s).0;
+            if best == f64::MIN { acc } else { acc.max((best - current_values[*s]).abs()) }
+        })
+    }
+
+    pub fn update_value(&mut self) -> f64 {
 
         let new_values:Vec<f64> = self.state_space.get_all_states()
         .map(|s| {
@@ -95,11 +262,11 @@ impl <'a, S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
             self.state_space.get_actions_at_state(s)
             .iter()
             .fold(f64::MIN, |acc:f64, action:&Action| 
-                acc.max(self.q(&self.learned_values.borrow(), s, action))
+                acc.max(self.q(&self.learned_values, s, action))
             ) 
         }).collect::<Vec<f64>>();
         
-        let mut learned_mut = self.learned_values.borrow_mut();
+        let learned_mut = &mut self.learned_values;
         let max_diff: f64 = new_values.into_iter().enumerate().fold(
             0., |acc:f64, (i, v)| {
                 if self.state_space.is_terminal_state(&i) {
@@ -114,70 +281,149 @@ impl <'a, S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
         max_diff
     }
 
-    pub fn value_iteration(&mut self, epislon:f64) -> Policy {
+    pub fn value_iteration(&mut self, epislon:f64) -> Result<Solution, SolveError> {
+        self.value_iteration_with(epislon, &mut ())
+    }
+
+    fn value_iteration_with(&mut self, epislon:f64, observer:&mut dyn SolverObserver) -> Result<Solution, SolveError> {
 
         self.reset_values();
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        let converged:bool = match self.config.backup {
+            BackupScheme::JACOBI => self.jacobi_value_iteration(epislon, &mut sweeps),
+            BackupScheme::GAUSS_SEIDEL => self.in_place_value_iteration(epislon, &mut sweeps, None),
+            BackupScheme::RANDOM_ORDER(seed) => self.in_place_value_iteration(epislon, &mut sweeps, Some(seed)),
+            BackupScheme::PRIORITIZED => self.prioritized_value_iteration(epislon, &mut sweeps),
+        };
+        self.finish(sweeps, self.greedy_policy(&self.learned_values), converged)
+    }
+
+    fn jacobi_value_iteration(&mut self, epislon:f64, sweeps:&mut Sweeps) -> bool {
         loop {
             // let mut new_values:Vec<f64> = Vec::with_capacity(self.state_space.len());
             // Non-parallel version is faster for small games. This is expected.
-            let new_values:Vec<f64> = self.state_space.get_all_states()
+            let (new_values, greedy):(Vec<f64>, Policy) = self.state_space.get_all_states()
             .map(|s| {
                 // find the action that has the highest Q value.
-                // Terminal states will have f64::Min as its value.
-                self.state_space.get_actions_at_state(s)
-                .iter()
-                .fold(f64::MIN, |acc:f64, action:&Action| 
-                    acc.max(self.q(&self.learned_values.borrow(), s, action))
-                ) 
-            }).collect::<Vec<f64>>();
+                // Terminal states are skipped below, their backup is never used.
+                if self.state_space.is_terminal_state(s) {
+                    (f64::MIN, self.default_action)
+                } else {
+                    self.best_action(&self.learned_values, s)
+                }
+            }).unzip();
             
-            let mut learned_mut = self.learned_values.borrow_mut();
-            let max_diff: f64 = new_values.into_iter().enumerate().fold(
-                0., |acc:f64, (i, v)| {
+            let learned_mut = &mut self.learned_values;
+            // smallest and largest change, terminal states do not change
+            let (lo, hi): (f64, f64) = new_values.into_iter().enumerate().fold(
+                (0., 0.), |(lo, hi):(f64, f64), (i, v)| {
                     if self.state_space.is_terminal_state(&i) {
-                        acc
+                        (lo, hi)
                     } else {
-                        let abs_diff: f64 = (v - learned_mut[i]).abs();
+                        let diff: f64 = v - learned_mut[i];
                         learned_mut[i] = v; // side effect
-                        acc.max(abs_diff)
+                        (lo.min(diff), hi.max(diff))
                     }
                 }
             );
-            if max_diff < epislon {
-                break
+            let within_limits:bool = sweeps.record(hi.max(-lo), &self.learned_values, &greedy);
+            if self.config.stopping.is_met(lo, hi, epislon, self.gamma) {
+                return true
+            }
+            if !within_limits {
+                return false
             }
         }
+    }
 
-        self.state_space.get_all_states()
-        .map(|s| {
+    pub fn par_value_iteration(&mut self, epislon:f64) -> Result<Solution, SolveError> {
+        self.par_value_iteration_with(epislon, &mut ())
+    }
+
+    fn par_value_iteration_with(&mut self, epislon:f64, observer:&mut dyn SolverObserver) -> Result<Solution, SolveError> {
+        // Same Jacobi-style sweep as value_iteration, but the Bellman backups of
+        // all states are computed across rayon threads. Each state's backup is
+        // still a sequential fold, so the result is identical to value_iteration
+        // with BackupScheme::JACOBI. The backup scheme of the config is ignored.
+        self.reset_values();
+        let states:Vec<&State> = self.state_space.get_all_states().collect();
+        let mut backups:Vec<(f64, Action)> = Vec::with_capacity(states.len());
+        let mut greedy:Policy = Vec::with_capacity(states.len());
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        let converged:bool = loop {
+            states.par_iter().map(|s| {
+                if self.state_space.is_terminal_state(s) {
+                    (f64::MIN, self.default_action)
+                } else {
+                    self.best_action(&self.learned_values, s)
+                }
+            }).collect_into_vec(&mut backups);
+
+            let (lo, hi):(f64, f64) = self.learned_values.par_iter_mut()
+                .zip(backups.par_iter())
+                .enumerate()
+                .filter(|(i, _)| !self.state_space.is_terminal_state(i))
+                .map(|(_, (old, (new, _)))| {
+                    let diff:f64 = *new - *old;
+                    *old = *new; // side effect
+                    (diff, diff)
+                }).reduce(|| (0., 0.), |(lo_a, hi_a), (lo_b, hi_b)| (lo_a.min(lo_b), hi_a.max(hi_b)));
+            backups.par_iter().map(|(_, a)| *a).collect_into_vec(&mut greedy);
+            let within_limits:bool = sweeps.record(hi.max(-lo), &self.learned_values, &greedy);
+            if self.config.stopping.is_met(lo, hi, epislon, self.gamma) {
+                break true
+            }
+            if !within_limits {
+                break false
+            }
+        };
+
+        let mut optimal_policy:Policy = Vec::with_capacity(states.len());
+        states.par_iter().map(|s| {
             if self.state_space.is_terminal_state(s) {
                 self.default_action
             } else {
-                self.best_action(&self.learned_values.borrow(), s).1
+                self.best_action(&self.learned_values, s).1
             }
-        }).collect::<Vec<Action>>()
+        }).collect_into_vec(&mut optimal_policy);
+        self.finish(sweeps, optimal_policy, converged)
+    }
+
+    fn evaluation_sweep(&mut self, pi:&[Action]) -> f64 {
+        // One in-place (Gauss-Seidel) sweep of the Bellman expectation backup for pi.
+        // Take the values out so that q can still borrow self.
+        let mut learned_mut = std::mem::take(&mut self.learned_values);
+        let max_diff:f64 = self.state_space.get_all_states()
+            .fold(0., |acc, s|{
+                if self.state_space.is_terminal_state(s){
+                    acc
+                } else {
+                    let old_v:f64 = learned_mut[*s];
+                    // side effect, notice here we refer to the action given by pi
+                    let new_val: f64 = self.q(&learned_mut, s, &pi[*s]);
+                    learned_mut[*s] = new_val;
+                    acc.max((old_v - new_val).abs())
+                }
+        });
+        self.learned_values = learned_mut;
+        max_diff
+    }
+
+    pub fn policy_iteration(&mut self, epsilon:f64) -> Result<Solution, SolveError> {
+        self.policy_iteration_with(epsilon, &mut ())
     }
 
-    pub fn policy_iteration(&mut self, epsilon:f64) -> Policy {
+    fn policy_iteration_with(&mut self, epsilon:f64, observer:&mut dyn SolverObserver) -> Result<Solution, SolveError> {
 
         self.reset_values();
         let state_count:usize = self.state_space.len();
         let mut pi:Vec<Action> = vec![self.default_action; state_count];
-        loop {
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        let converged:bool = 'improve: loop {
             loop {
-                let mut learned_mut = self.learned_values.borrow_mut();
-                let max_diff:f64 = self.state_space.get_all_states()
-                    .fold(0., |acc, s|{
-                        if self.state_space.is_terminal_state(s){
-                            acc
-                        } else {
-                            let old_v:f64 = learned_mut[*s];
-                            // side effect, notice here we refer to the action given by pi
-                            let new_val: f64 = self.q(&learned_mut, s, &pi[*s]);
-                            learned_mut[*s] = new_val;
-                            acc.max((old_v - new_val).abs())
-                        }
-                });
+                let max_diff:f64 = self.evaluation_sweep(&pi);
+                let within_limits:bool = sweeps.record(max_diff, &self.learned_values, &pi);
+                if !within_limits {break 'improve false}
                 if max_diff < epsilon {break}
             }
 
@@ -185,7 +431,7 @@ impl <'a, S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
             pi.iter_mut().enumerate().for_each(|(i, p): (usize, &mut usize)| {
                 if !self.state_space.is_terminal_state(&i){
                     let old_action:Action = *p;
-                    let new_action:Action = self.best_action(&self.learned_values.borrow(), &i).1;
+                    let new_action:Action = self.best_action(&self.learned_values, &i).1;
                     *p = new_action;
                     if stable {
                         stable = new_action == old_action; // side effect
@@ -193,9 +439,266 @@ impl <'a, S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
                 }
             });
             if stable {
-                return pi;
+                sweeps.set_residual(self.bellman_residual(&self.learned_values));
+                break true
+            }
+        };
+        self.finish(sweeps, pi, converged)
+    }
+
+    pub fn evaluate_policy(&self, pi:&Policy) -> Result<Vec<f64>, SolveError> {
+        // SolveError::Singular if the system cannot be solved, e.g. gamma = 1 and pi
+        // never terminates.
+        self.try_evaluate_policy(pi).ok_or(SolveError::Singular)
+    }
+
+    fn try_evaluate_policy(&self, pi:&Policy) -> Option<Vec<f64>> {
+        // Solves (I - gamma * P_pi) v = r_pi directly. Terminal states are worth 0.
+        // Small state spaces are solved densely, large ones with sparse BiCGSTAB.
+        let (rows, rewards):(Vec<Vec<(State, f64)>>, Vec<f64>) = self.state_space.get_all_states()
+        .map(|s| {
+            let mut row:Vec<(State, f64)> = vec![(*s, 1.)];
+            let mut reward:f64 = 0.;
+            if !self.state_space.is_terminal_state(s) {
+                for (next, p, r) in self.state_space.get_future_rewards(s, &pi[*s]) {
+                    row.push((next, -self.gamma * p));
+                    reward += p * r;
+                }
+            }
+            (row, reward)
+        }).unzip();
+
+        // warm start from the current values, policy iteration changes them little
+        let x0 = Array1::from_vec(self.learned_values.clone());
+        linalg::solve_rows(rows, Array1::from_vec(rewards), x0).map(|v| v.to_vec())
+    }
+
+    pub fn exact_policy_iteration(&mut self) -> Result<Solution, SolveError> {
+        self.exact_policy_iteration_with(&mut ())
+    }
+
+    fn exact_policy_iteration_with(&mut self, observer:&mut dyn SolverObserver) -> Result<Solution, SolveError> {
+        // Policy iteration where each policy is evaluated exactly by evaluate_policy.
+        // Every evaluation and improvement counts as one iteration. Actions are only
+        // switched on a strict improvement, so pi has to start with available actions.
+        self.reset_values();
+        let mut pi:Policy = self.initial_policy();
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        let converged:bool = loop {
+            self.learned_values = self.evaluate_policy(&pi)?;
+            let residual:f64 = self.bellman_residual(&self.learned_values);
+            let within_limits:bool = sweeps.record(residual, &self.learned_values, &pi);
+            let mut stable:bool = true;
+            pi.iter_mut().enumerate().for_each(|(i, p): (usize, &mut usize)| {
+                if !self.state_space.is_terminal_state(&i){
+                    let (best_v, new_action):(f64, Action) = self.best_action(&self.learned_values, &i);
+                    // only switch on a strict improvement, so that round-off cannot cycle
+                    if new_action != *p && best_v > self.q(&self.learned_values, &i, p) + 1e-12 {
+                        *p = new_action;
+                        stable = false; // side effect
+                    }
+                }
+            });
+            if stable {
+                break true
+            }
+            if !within_limits {
+                break false
+            }
+        };
+        self.finish(sweeps, pi, converged)
+    }
+
+    pub fn occupancy_measure(&self) -> Result<(OccupancyMeasure, Vec<f64>), LpError> {
+        // Solves the dual LP of the MDP over the discounted state-action occupancy x(s, a):
+        //   max sum_{s,a} x(s,a) r(s,a)
+        //   s.t. sum_a x(s',a) - gamma * sum_{s,a} p(s'|s,a) x(s,a) = 1  for every non-terminal s'
+        //        x >= 0.
+        // Returns every (s, a, x(s, a)) and the optimal values, which are the LP duals.
+        // Terminal states (and states without actions) carry no constraint and are worth 0.
+        let n:usize = self.state_space.len();
+        let mut row_of:Vec<Option<usize>> = vec![None; n];
+        let mut pairs:Vec<(State, Action)> = Vec::new();
+        let mut m:usize = 0; // number of constraint rows so far
+        for s in self.state_space.get_all_states() {
+            let actions:Vec<Action> = self.state_space.get_actions_at_state(s);
+            if !self.state_space.is_terminal_state(s) && !actions.is_empty() {
+                row_of[*s] = Some(m);
+                m += 1;
+                pairs.extend(actions.into_iter().map(|a| (*s, a)));
             }
         }
+
+        let mut a = Array2::<f64>::zeros((m, pairs.len()));
+        let mut c = Array1::<f64>::zeros(pairs.len());
+        for (j, (s, action)) in pairs.iter().enumerate() {
+            if let Some(i) = row_of[*s] {
+                a[[i, j]] += 1.;
+            }
+            for (next, p, r) in self.state_space.get_future_rewards(s, action) {
+                if let Some(i) = row_of[next] {
+                    a[[i, j]] -= self.gamma * p;
+                }
+                c[j] += p * r;
+            }
+        }
+        let solution = simplex::maximize(&a, &Array1::ones(m), &c)?;
+
+        let values:Vec<f64> = row_of.iter()
+            .map(|row| row.map_or(0., |i| solution.y[i]))
+            .collect();
+        let occupancy = pairs.into_iter()
+            .zip(solution.x)
+            .map(|((s, a), x)| (s, a, x))
+            .collect();
+        Ok((occupancy, values))
+    }
+
+    pub fn linear_program(&mut self) -> Result<Solution, SolveError> {
+        self.linear_program_with(&mut ())
+    }
+
+    fn linear_program_with(&mut self, observer:&mut dyn SolverObserver) -> Result<Solution, SolveError> {
+        // Solves the dual LP, the optimal action at each state is the one carrying
+        // its occupancy measure. The whole simplex run counts as one iteration.
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        let (occupancy, values) = self.occupancy_measure()?;
+        let mut best:Vec<(f64, Action)> = vec![(f64::MIN, self.default_action); self.state_space.len()];
+        for (s, a, x) in occupancy {
+            if x > best[s].0 {
+                best[s] = (x, a);
+            }
+        }
+        self.learned_values = values;
+        let pi:Policy = best.into_iter().map(|(_, a)| a).collect();
+        sweeps.record(self.bellman_residual(&self.learned_values), &self.learned_values, &pi);
+        self.finish(sweeps, pi, true)
+    }
+
+    pub fn modified_policy_iteration(&mut self, epsilon:f64, sweeps:usize) -> Result<Solution, SolveError> {
+        self.modified_policy_iteration_with(epsilon, sweeps, &mut ())
+    }
+
+    fn modified_policy_iteration_with(&mut self, epsilon:f64, sweeps:usize, observer:&mut dyn SolverObserver) -> Result<Solution, SolveError> {
+        // Each improvement step is a full Bellman optimality backup, which is followed
+        // by only `sweeps` evaluation sweeps of the greedy policy instead of evaluating
+        // it to epsilon. sweeps = 0 is value iteration, sweeps -> inf is policy iteration.
+        self.reset_values();
+        let state_count:usize = self.state_space.len();
+        let mut pi:Vec<Action> = vec![self.default_action; state_count];
+        let mut counter = Sweeps::new(&self.config, observer);
+        let converged:bool = 'improve: loop {
+            let improved:Vec<(f64, Action)> = self.state_space.get_all_states()
+            .map(|s| {
+                if self.state_space.is_terminal_state(s) {
+                    (self.learned_values[*s], self.default_action)
+                } else {
+                    self.best_action(&self.learned_values, s)
+                }
+            }).collect();
+
+            let (lo, hi):(f64, f64) = improved.into_iter().enumerate().fold(
+                (0., 0.), |(lo, hi):(f64, f64), (i, (v, a))| {
+                    let diff:f64 = v - self.learned_values[i];
+                    self.learned_values[i] = v; // side effect
+                    pi[i] = a;
+                    (lo.min(diff), hi.max(diff))
+                }
+            );
+            let within_limits:bool = counter.record(hi.max(-lo), &self.learned_values, &pi);
+            if self.config.stopping.is_met(lo, hi, epsilon, self.gamma) {
+                break true
+            }
+            if !within_limits {
+                break false
+            }
+
+            for _ in 0..sweeps {
+                self.evaluation_sweep(&pi);
+                if !counter.tick(&self.learned_values, &pi) {
+                    break 'improve false
+                }
+            }
+        };
+        self.finish(counter, pi, converged)
+    }
+
+    pub fn par_policy_iteration(&mut self, epsilon:f64) -> Result<Solution, SolveError> {
+        self.par_policy_iteration_with(epsilon, &mut ())
+    }
+
+    fn par_policy_iteration_with(&mut self, epsilon:f64, observer:&mut dyn SolverObserver) -> Result<Solution, SolveError> {
+        // Gauss-Seidel evaluation cannot be split across threads, so the policy
+        // is evaluated with Jacobi sweeps instead. Improvement is done per state
+        // in parallel and the loop stops once the policy no longer changes.
+        self.reset_values();
+        let states:Vec<&State> = self.state_space.get_all_states().collect();
+        let mut pi:Policy = vec![self.default_action; states.len()];
+        let mut new_values:Vec<f64> = Vec::with_capacity(states.len());
+        let mut new_pi:Policy = Vec::with_capacity(states.len());
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        let converged:bool = 'improve: loop {
+            loop {
+                states.par_iter().map(|s| {
+                    if self.state_space.is_terminal_state(s) {
+                        self.learned_values[**s]
+                    } else {
+                        self.q(&self.learned_values, s, &pi[**s])
+                    }
+                }).collect_into_vec(&mut new_values);
+
+                let max_diff:f64 = self.learned_values.par_iter_mut()
+                    .zip(new_values.par_iter())
+                    .map(|(old, new)| {
+                        let abs_diff:f64 = (*new - *old).abs();
+                        *old = *new; // side effect
+                        abs_diff
+                    }).reduce(|| 0., f64::max);
+                let within_limits:bool = sweeps.record(max_diff, &self.learned_values, &pi);
+                if !within_limits {break 'improve false}
+                if max_diff < epsilon {break}
+            }
+
+            states.par_iter().map(|s| {
+                if self.state_space.is_terminal_state(s) {
+                    pi[**s]
+                } else {
+                    self.best_action(&self.learned_values, s).1
+                }
+            }).collect_into_vec(&mut new_pi);
+
+            if pi == new_pi {
+                sweeps.set_residual(self.bellman_residual(&self.learned_values));
+                break true
+            }
+            std::mem::swap(&mut pi, &mut new_pi);
+        };
+        self.finish(sweeps, pi, converged)
+    }
+
+    pub fn backward_induction(&mut self, horizon:usize) -> (Vec<Policy>, Vec<Vec<f64>>) {
+        // Finite horizon: policies[t] and values[t] are the optimal decision rule and
+        // value-to-go at time step t, with horizon - t steps left. Values after the last
+        // step are 0. learned_values is set to values[0].
+        let state_count:usize = self.state_space.len();
+        let mut policies:Vec<Policy> = vec![Vec::new(); horizon];
+        let mut values:Vec<Vec<f64>> = vec![Vec::new(); horizon];
+        let mut next_values:Vec<f64> = vec![0.; state_count];
+        for t in (0..horizon).rev() {
+            let (v_t, pi_t):(Vec<f64>, Policy) = self.state_space.get_all_states()
+            .map(|s| {
+                if self.state_space.is_terminal_state(s) {
+                    (next_values[*s], self.default_action)
+                } else {
+                    self.best_action(&next_values, s)
+                }
+            }).unzip();
+            next_values = v_t.clone();
+            values[t] = v_t;
+            policies[t] = pi_t;
+        }
+        self.learned_values = next_values;
+        (policies, values)
     }
 
     pub fn get_state_space(&self) -> &S {
@@ -203,26 +706,480 @@ impl <'a, S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
     }
 
     pub fn get_learned_values(&self) -> Vec<f64> {
-        let values = self.learned_values.clone();
-        values.take()
+        self.learned_values.clone()
+    }
+
+    pub fn get_q_table(&self) -> QTable {
+        // Q values implied by the learned values, Q(s, a) = sum p * (r + gamma * V(s')),
+        // in the order of get_actions_at_state. Terminal states have no entries.
+        self.state_space.get_all_states()
+        .map(|s| {
+            if self.state_space.is_terminal_state(s) {
+                Vec::new()
+            } else {
+                self.state_space.get_actions_at_state(s)
+                .into_iter()
+                .map(|a| (a, self.q(&self.learned_values, s, &a)))
+                .collect()
+            }
+        }).collect()
+    }
+
+    pub fn get_policy_set(&self, tolerance:f64) -> PolicySet {
+        // Every action whose Q value under the learned values is within tolerance of
+        // the best one, in the order of get_actions_at_state. Terminal states have none.
+        self.get_q_table()
+        .into_iter()
+        .map(|row| {
+            let best:f64 = row.iter().fold(f64::MIN, |acc, (_, q)| acc.max(*q));
+            row.into_iter()
+            .filter(|(_, q)| *q >= best - tolerance)
+            .map(|(a, _)| a)
+            .collect()
+        }).collect()
+    }
+
+    pub fn get_advantages(&self) -> QTable {
+        // A(s, a) = Q(s, a) - max_b Q(s, b), so the best action has advantage 0.
+        self.get_q_table()
+        .into_iter()
+        .map(|row| {
+            let best:f64 = row.iter().fold(f64::MIN, |acc, (_, q)| acc.max(*q));
+            row.into_iter().map(|(a, q)| (a, q - best)).collect()
+        }).collect()
+    }
+
+    pub fn get_action_gaps(&self) -> Vec<f64> {
+        // Difference between the best and the second best Q value at each state.
+        // States with fewer than two actions have an infinite gap.
+        self.get_q_table()
+        .into_iter()
+        .map(|row| {
+            if row.len() < 2 {
+                return f64::INFINITY
+            }
+            let (first, second):(f64, f64) = row.iter()
+                .fold((f64::NEG_INFINITY, f64::NEG_INFINITY), |(first, second), (_, q)| {
+                    if *q > first { (*q, first) } else { (first, second.max(*q)) }
+                });
+            first - second
+        }).collect()
     }
 
     pub fn show_learned_values(&self) {
         println!("{:?}", self.learned_values);
     }
 
-    pub fn solve(&mut self, method:MDPSolver, epsilon:f64) -> Policy {
+    pub fn solve(&mut self, method:MDPSolver, epsilon:f64) -> Result<Solution, SolveError> {
+        self.solve_observed(method, epsilon, &mut ())
+    }
+
+    /// Like solve, calling observer after every sweep of the solver.
+    pub fn solve_observed(&mut self, method:MDPSolver, epsilon:f64, observer:&mut dyn SolverObserver) -> Result<Solution, SolveError> {
         self.reset_values();
         println!("\nSolve by {:?}", method);
         match method {
             MDPSolver::POLICY_ITER => {
-                self.policy_iteration(epsilon)
+                self.policy_iteration_with(epsilon, observer)
+            }
+            MDPSolver::PAR_POLICY_ITER => {
+                self.par_policy_iteration_with(epsilon, observer)
+            }
+            MDPSolver::MODIFIED_POLICY_ITER(sweeps) => {
+                self.modified_policy_iteration_with(epsilon, sweeps, observer)
+            }
+            MDPSolver::EXACT_POLICY_ITER => {
+                self.exact_policy_iteration_with(observer)
+            }
+            MDPSolver::LINEAR_PROGRAM => {
+                self.linear_program_with(observer)
+            }
+            MDPSolver::RELATIVE_VALUE_ITER => {
+                self.relative_value_iteration_with(epsilon, observer).map(|(solution, _)| solution)
+            }
+            MDPSolver::AVERAGE_POLICY_ITER => {
+                self.average_reward_policy_iteration_with(observer).map(|(solution, _)| solution)
             }
             MDPSolver::VALUE_ITER => {
-                self.value_iteration(epsilon)
+                self.value_iteration_with(epsilon, observer)
+            }
+            MDPSolver::PAR_VALUE_ITER => {
+                self.par_value_iteration_with(epsilon, observer)
+            }
+            MDPSolver::SOFT_VALUE_ITER(temperature) => {
+                self.soft_value_iteration_with(epsilon, temperature, observer).map(|(solution, _)| solution)
+            }
+        }
+    }
+
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linalg::DENSE_SOLVE_LIMIT;
+    use crate::examples::{dien::DieN, grid_world::GridWorld};
+
+    fn assert_same_as_sequential<S: StateSpace + std::marker::Sync>(mdp:&mut MarkovDecisionProcess<S>) {
+        let sequential:Solution = mdp.value_iteration(1e-6).unwrap();
+        let parallel:Solution = mdp.par_value_iteration(1e-6).unwrap();
+        assert_eq!(sequential.policy, parallel.policy);
+        // bit for bit, not just within epsilon
+        let bits = |values:&[f64]| values.iter().map(|v| v.to_bits()).collect::<Vec<u64>>();
+        assert_eq!(bits(&sequential.values), bits(&parallel.values));
+        assert_eq!(sequential.iterations, parallel.iterations);
+    }
+
+    #[test]
+    fn par_value_iteration_matches_value_iteration() {
+        assert_same_as_sequential(&mut MarkovDecisionProcess::new(GridWorld::default(), 0, 0.9));
+        assert_same_as_sequential(&mut MarkovDecisionProcess::new(GridWorld::with_size(12, 9), 0, 0.95));
+        assert_same_as_sequential(&mut MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 1.0));
+        assert_same_as_sequential(&mut MarkovDecisionProcess::new(DieN::new(vec![0,1,0,1,0,0,1,0]), 0, 1.0));
+    }
+
+    #[test]
+    fn sparse_policy_evaluation_matches_policy_iteration() {
+        // more than DENSE_SOLVE_LIMIT states, so evaluate_policy runs BiCGSTAB
+        let mut mdp = MarkovDecisionProcess::new(GridWorld::with_size(40, 30), 0, 0.9);
+        assert!(mdp.get_state_space().len() > DENSE_SOLVE_LIMIT);
+        let exact:Solution = mdp.exact_policy_iteration().unwrap();
+        let iterative:Solution = mdp.policy_iteration(1e-12).unwrap();
+        // the grid has many equally short paths, so only the values have to agree
+        for (a, b) in exact.values.iter().zip(&iterative.values) {
+            assert!((a - b).abs() < 1e-8);
+        }
+        // and each value is consistent with the Bellman equation of its policy
+        let values:Vec<f64> = mdp.evaluate_policy(&exact.policy).unwrap();
+        let residual:f64 = StateSpace::get_all_states(mdp.get_state_space())
+            .filter(|s| !StateSpace::is_terminal_state(mdp.get_state_space(), s))
+            .map(|s| (mdp.q(&values, s, &exact.policy[*s]) - values[*s]).abs())
+            .fold(0., f64::max);
+        assert!(residual < 1e-9);
+    }
+    fn assert_lp_matches_value_iteration<S: StateSpace + std::marker::Sync>(mdp:&mut MarkovDecisionProcess<S>) {
+        mdp.set_solver_config(SolverConfig { stopping: StoppingRule::EPSILON_OPTIMAL, ..SolverConfig::default() });
+        let iterated:Solution = mdp.value_iteration(1e-10).unwrap();
+        let lp:Solution = mdp.linear_program().unwrap();
+        for (a, b) in lp.values.iter().zip(&iterated.values) {
+            assert!((a - b).abs() < 1e-8, "{:?} != {:?}", lp.values, iterated.values);
+        }
+        // the LP policy is optimal, even where it breaks ties differently
+        for (a, b) in mdp.evaluate_policy(&lp.policy).unwrap().iter().zip(&iterated.values) {
+            assert!((a - b).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn linear_program_matches_value_iteration() {
+        assert_lp_matches_value_iteration(&mut MarkovDecisionProcess::new(GridWorld::default(), 0, 0.9));
+        assert_lp_matches_value_iteration(&mut MarkovDecisionProcess::new(GridWorld::with_size(6, 5), 0, 0.95));
+        assert_lp_matches_value_iteration(&mut MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 0.9));
+        assert_lp_matches_value_iteration(&mut MarkovDecisionProcess::new(DieN::new(vec![0,1,0,1,0,0,1,0]), 0, 0.99));
+    }
+
+    #[test]
+    fn solutions_record_whether_the_observer_stopped_them() {
+        let mut mdp = MarkovDecisionProcess::new(GridWorld::default(), 0, 0.9);
+        let converged:Solution = mdp.solve(MDPSolver::VALUE_ITER, 1e-9).unwrap();
+        assert!(!converged.stopped_by_observer);
+
+        let mut stop_after_three = |info:&SweepInfo| {
+            if info.iteration == 3 { SweepSignal::STOP } else { SweepSignal::CONTINUE }
+        };
+        for method in [MDPSolver::VALUE_ITER, MDPSolver::POLICY_ITER, MDPSolver::MODIFIED_POLICY_ITER(5)] {
+            let stopped:Solution = mdp.solve_observed(method, 1e-9, &mut stop_after_three).unwrap();
+            assert!(stopped.stopped_by_observer);
+            assert_eq!(stopped.iterations, 3);
+        }
+
+        // a stop on an evaluation sweep that also meets epsilon ends the solve as well
+        let mut stop_at_first = |info:&SweepInfo| {
+            if info.iteration == 1 { SweepSignal::STOP } else { SweepSignal::CONTINUE }
+        };
+        for method in [MDPSolver::POLICY_ITER, MDPSolver::PAR_POLICY_ITER] {
+            let stopped:Solution = mdp.solve_observed(method, 1e-9, &mut stop_at_first).unwrap();
+            assert!(stopped.stopped_by_observer);
+            assert_eq!(stopped.iterations, 1);
+        }
+    }
+
+    #[test]
+    fn soft_value_iteration_rejects_bad_temperatures() {
+        let mut mdp = MarkovDecisionProcess::new(GridWorld::default(), 0, 0.9);
+        for temperature in [0., -1., f64::NAN, f64::INFINITY] {
+            match mdp.solve(MDPSolver::SOFT_VALUE_ITER(temperature), 1e-6) {
+                Err(SolveError::InvalidTemperature(t)) => assert!(t.to_bits() == temperature.to_bits()),
+                other => panic!("expected InvalidTemperature, got {:?}", other),
+            }
+        }
+        assert!(mdp.solve(MDPSolver::SOFT_VALUE_ITER(0.5), 1e-6).is_ok());
+    }
+
+    #[test]
+    fn evaluate_policy_reports_singular_systems() {
+        // state 0 loops with reward 1, state 1 ends after a reward of 2
+        let mut builder = TabularMdp::builder(3);
+        builder
+            .add_transition(0, 0, 0, 1., 1.)
+            .add_transition(1, 0, 2, 1., 2.)
+            .mark_terminal(2);
+        let mdp = MarkovDecisionProcess::new(builder.build(), 0, 0.5);
+        assert_eq!(mdp.evaluate_policy(&vec![0, 0, 0]).unwrap(), vec![2., 2., 0.]);
+        let undiscounted = MarkovDecisionProcess::new(mdp.state_space, 0, 1.);
+        assert!(matches!(undiscounted.evaluate_policy(&vec![0, 0, 0]), Err(SolveError::Singular)));
+    }
+    #[test]
+    fn backward_induction_approaches_the_infinite_horizon() {
+        let mut mdp = MarkovDecisionProcess::new(GridWorld::default(), 0, 0.9);
+        let solution:Solution = mdp.value_iteration(1e-12).unwrap();
+        let (policies, values) = mdp.backward_induction(200);
+        assert_eq!((policies.len(), values.len()), (200, 200));
+        assert_eq!(mdp.get_learned_values(), values[0]);
+        // 0.9^200 is below 1e-9
+        assert!(values[0].iter().zip(&solution.values).all(|(a, b)| (a - b).abs() < 1e-9));
+
+        let (policies, values) = mdp.backward_induction(0);
+        assert!(policies.is_empty() && values.is_empty());
+        assert!(mdp.get_learned_values().iter().all(|v| *v == 0.));
+    }
+
+    #[test]
+    fn backward_induction_with_one_step_is_the_one_step_backup() {
+        let die = DieN::new(vec![1,1,1,0,0,0]);
+        let expected:Vec<(f64, Action)> = StateSpace::get_all_states(&die)
+        .map(|s| {
+            if die.is_terminal_state(s) {
+                return (0., 0)
+            }
+            die.get_actions_at_state(s).into_iter()
+                .map(|a| (die.get_future_rewards(s, &a).iter().map(|(_, p, r)| p * r).sum::<f64>(), a))
+                .fold((f64::MIN, 0), |acc, (v, a)| if v > acc.0 { (v, a) } else { acc })
+        }).collect();
+        let mut mdp = MarkovDecisionProcess::new(die, 0, 1.0);
+        let (policies, values) = mdp.backward_induction(1);
+        assert_eq!(policies[0], expected.iter().map(|(_, a)| *a).collect::<Policy>());
+        assert!(values[0].iter().zip(&expected).all(|(v, (e, _))| (v - e).abs() < 1e-12));
+    }
+    // States 0..n - 1 all offer the actions 3, 1, 2, 0 in that order, each ending in
+    // the terminal state n. Actions 3, 1 and 0 earn 1, action 2 earns 1 - 1e-6.
+    struct Ties {
+        states: Vec<State>,
+    }
+
+    impl Ties {
+        fn new(n:usize) -> Self {
+            Ties { states: (0..=n).collect() }
+        }
+    }
+
+    impl StateSpace for Ties {
+        fn get_actions_at_state(&self, s:&State) -> Vec<Action> {
+            if self.is_terminal_state(s) { Vec::new() } else { vec![3, 1, 2, 0] }
+        }
+
+        fn get_future_rewards(&self, _s:&State, a:&Action) -> Vec<(State, f64, f64)> {
+            vec![(self.states.len() - 1, 1., if *a == 2 { 1. - 1e-6 } else { 1. })]
+        }
+
+        fn get_all_states(&self) -> Iter<'_, State> {
+            self.states.iter()
+        }
+
+        fn len(&self) -> usize {
+            self.states.len()
+        }
+
+        fn is_terminal_state(&self, s:&State) -> bool {
+            *s == self.states.len() - 1
+        }
+    }
+
+    fn solve_with_tie_break(default_action:Action, tie_break:TieBreak) -> Policy {
+        let mut mdp = MarkovDecisionProcess::new(Ties::new(50), default_action, 0.9);
+        mdp.set_solver_config(SolverConfig { tie_break, ..SolverConfig::default() });
+        let mut pi:Policy = mdp.solve(MDPSolver::VALUE_ITER, 1e-9).unwrap().policy;
+        assert_eq!(pi.pop(), Some(default_action)); // the terminal state
+        pi
+    }
+
+    #[test]
+    fn tie_breaks_pick_among_the_tied_actions() {
+        assert_eq!(solve_with_tie_break(1, TieBreak::FIRST), vec![3; 50]);
+        assert_eq!(solve_with_tie_break(1, TieBreak::LOWEST_INDEX), vec![0; 50]);
+        assert_eq!(solve_with_tie_break(1, TieBreak::PREFER_DEFAULT), vec![1; 50]);
+        // action 2 is not tied, so the lowest index wins
+        assert_eq!(solve_with_tie_break(2, TieBreak::PREFER_DEFAULT), vec![0; 50]);
+
+        let random:Policy = solve_with_tie_break(1, TieBreak::RANDOM(7));
+        assert_eq!(random, solve_with_tie_break(1, TieBreak::RANDOM(7)));
+        assert_ne!(random, solve_with_tie_break(1, TieBreak::RANDOM(8)));
+        for a in [3, 1, 0] {
+            assert!(random.contains(&a));
+        }
+        assert!(!random.contains(&2));
+    }
+
+    #[test]
+    fn policy_sets_respect_the_tolerance() {
+        let mut mdp = MarkovDecisionProcess::new(Ties::new(2), 0, 0.9);
+        mdp.solve(MDPSolver::VALUE_ITER, 1e-9).unwrap();
+        assert_eq!(mdp.get_policy_set(1e-9), vec![vec![3, 1, 0], vec![3, 1, 0], vec![]]);
+        assert_eq!(mdp.get_policy_set(1e-5), vec![vec![3, 1, 2, 0], vec![3, 1, 2, 0], vec![]]);
+    }
+    #[test]
+    fn q_tables_advantages_and_action_gaps() {
+        // state 0 has a tie, state 1 a single action, state 2 a clear best action
+        let mut builder = TabularMdp::builder(4);
+        builder
+            .add_transition(0, 0, 3, 1., 1.)
+            .add_transition(0, 1, 3, 1., 1.)
+            .add_transition(1, 0, 3, 1., 5.)
+            .add_transition(2, 0, 3, 1., 0.5)
+            .add_transition(2, 1, 3, 1., 2.)
+            .mark_terminal(3);
+        let mut mdp = MarkovDecisionProcess::new(builder.build(), 0, 0.9);
+        mdp.solve(MDPSolver::VALUE_ITER, 1e-9).unwrap();
+        assert_eq!(mdp.get_q_table(), vec![vec![(0, 1.), (1, 1.)], vec![(0, 5.)], vec![(0, 0.5), (1, 2.)], vec![]]);
+        assert_eq!(mdp.get_advantages(), vec![vec![(0, 0.), (1, 0.)], vec![(0, 0.)], vec![(0, -1.5), (1, 0.)], vec![]]);
+        assert_eq!(mdp.get_action_gaps(), vec![0., f64::INFINITY, 1.5, f64::INFINITY]);
+    }
+    // A random model with n - 1 decision states and the terminal state n - 1. Every
+    // action leads to up to three states with random probabilities and rewards.
+    fn random_model(rng:&mut StdRng) -> TabularMdp {
+        let n:usize = rng.gen_range(2..12);
+        let mut builder = TabularMdp::builder(n);
+        for s in 0..n - 1 {
+            for a in 0..rng.gen_range(1..4) {
+                let weights:Vec<(State, f64)> = (0..rng.gen_range(1..4))
+                    .map(|_| (rng.gen_range(0..n), rng.gen_range(0.1..1.)))
+                    .collect();
+                let total:f64 = weights.iter().map(|(_, w)| w).sum();
+                for (next, w) in weights {
+                    builder.add_transition(s, a, next, w / total, rng.gen_range(-1.0..1.));
+                }
+            }
+        }
+        builder.mark_terminal(n - 1);
+        builder.build()
+    }
+
+    fn max_gap(upper:&[f64], lower:&[f64]) -> f64 {
+        upper.iter().zip(lower).fold(0., |acc, (u, l)| acc.max(u - l))
+    }
+
+    #[test]
+    fn suboptimality_bounds_hold_on_random_models() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..300 {
+            let gamma:f64 = [0.5, 0.9, 0.99][rng.gen_range(0..3)];
+            let mut mdp = MarkovDecisionProcess::new(random_model(&mut rng), 0, gamma);
+            let optimal:Vec<f64> = mdp.exact_policy_iteration().unwrap().values;
+            // any values with 0 at the terminal state, and the greedy or a random policy
+            let n:usize = mdp.state_space.len();
+            let mut v:Vec<f64> = (0..n).map(|_| rng.gen_range(-5.0..5.)).collect();
+            v[n - 1] = 0.;
+            let greedy:Policy = mdp.greedy_policy(&v);
+            let random:Policy = (0..n).map(|s| {
+                let actions:Vec<Action> = mdp.state_space.get_actions_at_state(&s);
+                if actions.is_empty() { 0 } else { actions[rng.gen_range(0..actions.len())] }
+            }).collect();
+            for pi in [greedy, random] {
+                let loss:f64 = max_gap(&optimal, &mdp.evaluate_policy(&pi).unwrap());
+                assert!(mdp.suboptimality_bound(&pi, &v) >= loss - 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn stopping_rules_give_epsilon_optimal_policies() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let epsilon:f64 = 1e-3;
+        for _ in 0..100 {
+            let mut mdp = MarkovDecisionProcess::new(random_model(&mut rng), 0, 0.9);
+            let optimal:Vec<f64> = mdp.exact_policy_iteration().unwrap().values;
+            for stopping in [StoppingRule::EPSILON_OPTIMAL, StoppingRule::SPAN] {
+                mdp.set_solver_config(SolverConfig { stopping, ..SolverConfig::default() });
+                let solution:Solution = mdp.value_iteration(epsilon).unwrap();
+                let loss:f64 = max_gap(&optimal, &mdp.evaluate_policy(&solution.policy).unwrap());
+                assert!(loss <= epsilon && loss <= solution.bound + 1e-12);
+                if stopping == StoppingRule::EPSILON_OPTIMAL {
+                    assert!(solution.values.iter().zip(&optimal).all(|(v, o)| (v - o).abs() <= epsilon / 2.));
+                }
             }
+            mdp.set_solver_config(SolverConfig::default());
         }
+        // the thresholds themselves, at gamma = 0.5 they are 1, 0.5 and a span of 1
+        assert!(StoppingRule::MAX_CHANGE.is_met(-0.5, 0.9, 1., 0.5));
+        assert!(!StoppingRule::MAX_CHANGE.is_met(-1., 0., 1., 0.5));
+        assert!(StoppingRule::EPSILON_OPTIMAL.is_met(-0.4, 0.4, 1., 0.5));
+        assert!(!StoppingRule::EPSILON_OPTIMAL.is_met(0., 0.5, 1., 0.5));
+        assert!(StoppingRule::SPAN.is_met(-0.4, 0.5, 1., 0.5));
+        assert!(!StoppingRule::SPAN.is_met(-0.5, 0.6, 1., 0.5));
+    }
+    #[test]
+    fn backup_schemes_reach_the_jacobi_values() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let schemes = [BackupScheme::GAUSS_SEIDEL, BackupScheme::RANDOM_ORDER(3), BackupScheme::PRIORITIZED];
+        for _ in 0..300 {
+            let mut mdp = MarkovDecisionProcess::new(random_model(&mut rng), 0, 0.9);
+            mdp.set_solver_config(SolverConfig { stopping: StoppingRule::EPSILON_OPTIMAL, ..SolverConfig::default() });
+            let jacobi:Solution = mdp.value_iteration(1e-9).unwrap();
+            for backup in schemes {
+                mdp.set_solver_config(SolverConfig { stopping: StoppingRule::EPSILON_OPTIMAL, backup, ..SolverConfig::default() });
+                let solution:Solution = mdp.value_iteration(1e-9).unwrap();
+                assert!(solution.values.iter().zip(&jacobi.values).all(|(a, b)| (a - b).abs() < 1e-9), "{:?}", backup);
+            }
+        }
+    }
+    fn assert_same_as_policy_iteration<S, F>(mdp:&mut MarkovDecisionProcess<S>, epsilon:f64, mut solve:F)
+    where
+        S: StateSpace + std::marker::Sync,
+        F: FnMut(&mut MarkovDecisionProcess<S>) -> Solution
+    {
+        // the reference is evaluated far more precisely than epsilon
+        let reference:Solution = mdp.policy_iteration(epsilon * 1e-6).unwrap();
+        let solution:Solution = solve(mdp);
+        assert_eq!(solution.policy, reference.policy);
+        assert!(solution.values.iter().zip(&reference.values).all(|(a, b)| (a - b).abs() < epsilon));
     }
 
+    fn agreement_models() -> (Vec<MarkovDecisionProcess<GridWorld>>, Vec<MarkovDecisionProcess<DieN>>) {
+        (
+            vec![
+                MarkovDecisionProcess::new(GridWorld::default(), 0, 0.9),
+                MarkovDecisionProcess::new(GridWorld::with_size(12, 9), 0, 0.95),
+            ],
+            vec![
+                MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 1.0),
+                MarkovDecisionProcess::new(DieN::new(vec![0,1,0,1,0,0,1,0]), 0, 1.0),
+            ]
+        )
+    }
 
+    #[test]
+    fn par_policy_iteration_matches_policy_iteration() {
+        let (grids, dice) = agreement_models();
+        for mut mdp in grids {
+            assert_same_as_policy_iteration(&mut mdp, 1e-6, |m| m.par_policy_iteration(1e-6).unwrap());
+        }
+        for mut mdp in dice {
+            assert_same_as_policy_iteration(&mut mdp, 1e-6, |m| m.par_policy_iteration(1e-6).unwrap());
+        }
+    }
+    #[test]
+    fn modified_policy_iteration_matches_policy_iteration() {
+        for sweeps in [1, 5, 50] {
+            let (grids, dice) = agreement_models();
+            for mut mdp in grids {
+                // MAX_CHANGE alone leaves the values up to epsilon * gamma / (1 - gamma) off
+                mdp.set_solver_config(SolverConfig { stopping: StoppingRule::EPSILON_OPTIMAL, ..SolverConfig::default() });
+                assert_same_as_policy_iteration(&mut mdp, 1e-6, |m| m.modified_policy_iteration(1e-6, sweeps).unwrap());
+            }
+            for mut mdp in dice {
+                assert_same_as_policy_iteration(&mut mdp, 1e-6, |m| m.modified_policy_iteration(1e-6, sweeps).unwrap());
+            }
+        }
+    }
 }
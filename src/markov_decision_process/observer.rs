@@ -0,0 +1,38 @@
+//! Hooks into the solver loops. An observer sees the solver state after every
+//! sweep, e.g. to animate GridWorld::print_on_states or to log the residuals,
+//! and may end the solve early with its own stopping rule.
+
+use super::Action;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepSignal {
+    CONTINUE,
+    STOP // the solver returns Ok with the current iterate
+}
+
+pub struct SweepInfo<'a> {
+    pub iteration: usize, // starts at 1
+    pub residual: f64,
+    pub values: &'a [f64],
+    // For value iteration the greedy actions of the last backup, otherwise the
+    // policy that is being evaluated.
+    pub policy: &'a [Action],
+}
+
+pub trait SolverObserver {
+    fn after_sweep(&mut self, info:&SweepInfo) -> SweepSignal;
+}
+
+// The no-op observer.
+impl SolverObserver for () {
+    fn after_sweep(&mut self, _info:&SweepInfo) -> SweepSignal {
+        SweepSignal::CONTINUE
+    }
+}
+
+impl <F: FnMut(&SweepInfo) -> SweepSignal> SolverObserver for F {
+    fn after_sweep(&mut self, info:&SweepInfo) -> SweepSignal {
+        self(info)
+    }
+}
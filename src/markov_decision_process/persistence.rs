@@ -0,0 +1,216 @@
+//! Saving and loading of models, policies, values and solver results, e.g. to
+//! cache an expensive solve or to hand a policy to another program. Anything that
+//! implements serde's traits works: TabularMdp, Policy, Vec<f64>, Solution,
+//! SolveError, StochasticPolicy, SolverConfig. Both formats give back every f64 bit
+//! for bit. JSON is readable, BINARY (bincode with variable length integers) is
+//! faster to read and smaller for full precision numbers like solved values.
+
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path
+};
+
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    JSON,
+    BINARY
+}
+
+#[derive(Debug)]
+pub enum PersistError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+}
+
+impl Display for PersistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PersistError::Io(e) => write!(f, "io error: {}", e),
+            PersistError::Json(e) => write!(f, "json error: {}", e),
+            PersistError::Binary(e) => write!(f, "binary format error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PersistError {}
+
+impl From<std::io::Error> for PersistError {
+    fn from(e: std::io::Error) -> Self {
+        PersistError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for PersistError {
+    fn from(e: serde_json::Error) -> Self {
+        PersistError::Json(e)
+    }
+}
+
+impl From<bincode::Error> for PersistError {
+    fn from(e: bincode::Error) -> Self {
+        PersistError::Binary(e)
+    }
+}
+
+pub fn to_bytes<T:Serialize + ?Sized>(value:&T, format:Format) -> Result<Vec<u8>, PersistError> {
+    match format {
+        Format::JSON => Ok(serde_json::to_vec(value)?),
+        Format::BINARY => Ok(bincode::DefaultOptions::new().serialize(value)?),
+    }
+}
+
+pub fn from_bytes<T:DeserializeOwned>(bytes:&[u8], format:Format) -> Result<T, PersistError> {
+    match format {
+        Format::JSON => Ok(serde_json::from_slice(bytes)?),
+        Format::BINARY => Ok(bincode::DefaultOptions::new().deserialize(bytes)?),
+    }
+}
+
+/// Writes value to path, replacing the file if it exists.
+pub fn save<T:Serialize + ?Sized, P:AsRef<Path>>(value:&T, path:P, format:Format) -> Result<(), PersistError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        Format::JSON => serde_json::to_writer(&mut writer, value)?,
+        Format::BINARY => bincode::DefaultOptions::new().serialize_into(&mut writer, value)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load<T:DeserializeOwned, P:AsRef<Path>>(path:P, format:Format) -> Result<T, PersistError> {
+    let reader = BufReader::new(File::open(path)?);
+    match format {
+        Format::JSON => Ok(serde_json::from_reader(reader)?),
+        Format::BINARY => Ok(bincode::DefaultOptions::new().deserialize_from(reader)?),
+    }
+}
+
+/// For f64 fields that may be infinite or NaN, like Solution::bound. JSON has no
+/// such numbers (serde_json would write null and then fail to read it back), so
+/// human readable formats write them as the strings "inf", "-inf" and "NaN".
+pub(crate) mod non_finite {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Readable {
+        Number(f64),
+        Text(String),
+    }
+
+    pub fn serialize<S:Serializer>(x:&f64, serializer:S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() || x.is_finite() {
+            serializer.serialize_f64(*x)
+        } else {
+            serializer.serialize_str(&x.to_string())
+        }
+    }
+
+    pub fn deserialize<'de, D:Deserializer<'de>>(deserializer:D) -> Result<f64, D::Error> {
+        if !deserializer.is_human_readable() {
+            return f64::deserialize(deserializer)
+        }
+        match Readable::deserialize(deserializer)? {
+            Readable::Number(x) => Ok(x),
+            Readable::Text(text) => text.parse::<f64>().map_err(D::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::{dien::DieN, grid_world::GridWorld},
+        markov_decision_process::{
+            MarkovDecisionProcess,
+            MDPSolver,
+            Solution,
+            SolveError,
+            StateSpace,
+            TabularMdp
+        }
+    };
+
+    const FORMATS:[Format; 2] = [Format::JSON, Format::BINARY];
+
+    fn bits(values:&[f64]) -> Vec<u64> {
+        values.iter().map(|v| v.to_bits()).collect()
+    }
+
+    fn transitions_bits(mdp:&TabularMdp) -> Vec<(usize, usize, usize, u64, u64)> {
+        StateSpace::get_all_states(mdp)
+            .flat_map(|s| mdp.get_actions_at_state(s).into_iter().flat_map(move |a| {
+                mdp.get_future_rewards(s, &a).into_iter()
+                    .map(move |(next, p, r)| (*s, a, next, p.to_bits(), r.to_bits()))
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn solutions_round_trip_with_an_infinite_bound() {
+        // gamma = 1 has no suboptimality bound
+        let mut mdp = MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 1.0);
+        let solution:Solution = mdp.solve(MDPSolver::VALUE_ITER, 1e-9).unwrap();
+        assert_eq!(solution.bound, f64::INFINITY);
+        for format in FORMATS {
+            let back:Solution = from_bytes(&to_bytes(&solution, format).unwrap(), format).unwrap();
+            assert_eq!(back.policy, solution.policy);
+            assert_eq!(bits(&back.values), bits(&solution.values));
+            assert_eq!(back.iterations, solution.iterations);
+            assert_eq!(back.residual.to_bits(), solution.residual.to_bits());
+            assert_eq!(back.bound, f64::INFINITY);
+            assert_eq!(back.elapsed, solution.elapsed);
+            assert_eq!(back.stopped_by_observer, solution.stopped_by_observer);
+        }
+        // NaN survives as well
+        let error = SolveError::InvalidTemperature(f64::NAN);
+        for format in FORMATS {
+            match from_bytes::<SolveError>(&to_bytes(&error, format).unwrap(), format).unwrap() {
+                SolveError::InvalidTemperature(t) => assert!(t.is_nan()),
+                other => panic!("expected InvalidTemperature, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn tabular_models_round_trip() {
+        let mdp = TabularMdp::from_state_space(&GridWorld::default());
+        for format in FORMATS {
+            let back:TabularMdp = from_bytes(&to_bytes(&mdp, format).unwrap(), format).unwrap();
+            assert_eq!(transitions_bits(&back), transitions_bits(&mdp));
+            assert!(StateSpace::get_all_states(&mdp)
+                .all(|s| back.is_terminal_state(s) == mdp.is_terminal_state(s)));
+        }
+
+        let path = std::env::temp_dir().join(format!("markov_decision_tabular_{}.bin", std::process::id()));
+        save(&mdp, &path, Format::BINARY).unwrap();
+        let loaded:Result<TabularMdp, PersistError> = load(&path, Format::BINARY);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(transitions_bits(&loaded.unwrap()), transitions_bits(&mdp));
+    }
+
+    #[test]
+    fn tabular_models_with_out_of_range_next_states_are_rejected() {
+        let json = br#"{"rows":[[[0,[[5,1.0,0.0]]]]],"terminal":[false]}"#;
+        match from_bytes::<TabularMdp>(json, Format::JSON) {
+            Err(PersistError::Json(e)) => assert!(e.to_string().contains("next state 5 is out of range")),
+            Err(e) => panic!("expected a json error, got {}", e),
+            Ok(_) => panic!("expected an error"),
+        }
+        // the same rows and terminal flags, laid out as bincode writes TabularRows
+        type Rows = Vec<Vec<(usize, Vec<(usize, f64, f64)>)>>;
+        let rows:(Rows, Vec<bool>) = (vec![vec![(0, vec![(5, 1., 0.)])]], vec![false]);
+        let bytes:Vec<u8> = to_bytes(&rows, Format::BINARY).unwrap();
+        assert!(matches!(from_bytes::<TabularMdp>(&bytes, Format::BINARY), Err(PersistError::Binary(_))));
+        let ok:(Rows, Vec<bool>) = (vec![vec![(0, vec![(0, 1., 0.)])]], vec![false]);
+        assert!(from_bytes::<TabularMdp>(&to_bytes(&ok, Format::BINARY).unwrap(), Format::BINARY).is_ok());
+    }
+}
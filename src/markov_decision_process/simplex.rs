@@ -0,0 +1,231 @@
+//! A dense two-phase simplex method for linear programs in standard form,
+//! i.e. maximize c^T x subject to A x = b, x >= 0.
+//! Bland's rule is used for pivoting, so the method always terminates. This is
+//! meant for cross-checking the iterative solvers on small and medium models,
+//! not for very large problems.
+
+use ndarray::{s, Array1, Array2, Axis};
+use serde::{Deserialize, Serialize};
+
+const TOL: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LpError {
+    Infeasible,
+    Unbounded,
+}
+
+pub struct LpSolution {
+    /// Optimal primal solution.
+    pub x: Array1<f64>,
+    /// Optimal dual solution y, i.e. the solution of min b^T y s.t. A^T y >= c.
+    pub y: Array1<f64>,
+    pub objective: f64,
+}
+
+struct Tableau {
+    // m constraint rows followed by the objective row, last column is the rhs.
+    t: Array2<f64>,
+    basis: Vec<usize>,
+}
+
+impl Tableau {
+    fn rows(&self) -> usize {
+        self.basis.len()
+    }
+
+    fn rhs(&self) -> usize {
+        self.t.ncols() - 1
+    }
+
+    fn pivot(&mut self, row: usize, col: usize) {
+        let p = self.t[[row, col]];
+        self.t.row_mut(row).mapv_inplace(|v| v / p);
+        let pivot_row = self.t.row(row).to_owned();
+        for (i, mut r) in self.t.axis_iter_mut(Axis(0)).enumerate() {
+            if i != row {
+                let factor = r[col];
+                if factor != 0. {
+                    r.scaled_add(-factor, &pivot_row);
+                }
+            }
+        }
+        self.basis[row] = col;
+    }
+
+    /// Runs simplex iterations, only letting columns < `allowed` enter the basis.
+    fn optimize(&mut self, allowed: usize) -> Result<(), LpError> {
+        let m = self.rows();
+        let rhs = self.rhs();
+        loop {
+            // Bland's rule: the first column with a negative reduced cost enters
+            let entering = match (0..allowed).find(|j| self.t[[m, *j]] < -TOL) {
+                Some(j) => j,
+                None => return Ok(()),
+            };
+            // ratio test, ties broken by the smallest basic variable
+            let leaving = (0..m)
+                .filter(|i| self.t[[*i, entering]] > TOL)
+                .map(|i| (self.t[[i, rhs]] / self.t[[i, entering]], i))
+                .min_by(|(r1, i1), (r2, i2)| {
+                    if (r1 - r2).abs() <= TOL {
+                        self.basis[*i1].cmp(&self.basis[*i2])
+                    } else {
+                        r1.total_cmp(r2)
+                    }
+                });
+            match leaving {
+                Some((_, row)) => self.pivot(row, entering),
+                None => return Err(LpError::Unbounded),
+            }
+        }
+    }
+}
+
+/// Solves max c^T x s.t. a x = b, x >= 0.
+pub fn maximize(a: &Array2<f64>, b: &Array1<f64>, c: &Array1<f64>) -> Result<LpSolution, LpError> {
+    let (m, n) = a.dim();
+    // rows with a negative rhs are flipped so that the artificial basis is feasible
+    let signs: Array1<f64> = b.mapv(|v| if v < 0. { -1. } else { 1. });
+
+    let mut t = Array2::<f64>::zeros((m + 1, n + m + 1));
+    for i in 0..m {
+        t.slice_mut(s![i, ..n]).assign(&(&a.row(i) * signs[i]));
+        t[[i, n + i]] = 1.;
+        t[[i, n + m]] = b[i] * signs[i];
+    }
+    // Phase 1: maximize -sum(artificials), priced out against the artificial basis.
+    for i in 0..m {
+        let row = t.row(i).to_owned();
+        t.row_mut(m).scaled_add(-1., &row);
+    }
+    for i in 0..m {
+        t[[m, n + i]] = 0.;
+    }
+    let mut tableau = Tableau { t, basis: (n..n + m).collect() };
+    tableau.optimize(n + m)?;
+    if tableau.t[[m, n + m]] < -TOL * (1. + b.iter().map(|v| v.abs()).sum::<f64>()) {
+        return Err(LpError::Infeasible)
+    }
+    // Drive the remaining (zero level) artificials out of the basis where possible.
+    // Rows where this is impossible are redundant and are left alone.
+    for i in 0..m {
+        if tableau.basis[i] >= n {
+            if let Some(j) = (0..n).find(|j| tableau.t[[i, *j]].abs() > TOL) {
+                tableau.pivot(i, j);
+            }
+        }
+    }
+
+    // Phase 2: the objective row becomes -c priced out against the current basis.
+    tableau.t.row_mut(m).fill(0.);
+    tableau.t.slice_mut(s![m, ..n]).assign(&c.mapv(|v| -v));
+    for i in 0..m {
+        let j = tableau.basis[i];
+        let factor = tableau.t[[m, j]];
+        if factor != 0. {
+            let row = tableau.t.row(i).to_owned();
+            tableau.t.row_mut(m).scaled_add(-factor, &row);
+        }
+    }
+    tableau.optimize(n)?;
+
+    let mut x = Array1::<f64>::zeros(n);
+    for (i, j) in tableau.basis.iter().enumerate() {
+        if *j < n {
+            x[*j] = tableau.t[[i, n + m]];
+        }
+    }
+    // The reduced costs of the artificial columns are c_B B^-1, i.e. the duals.
+    let y = &tableau.t.slice(s![m, n..n + m]) * &signs;
+    Ok(LpSolution { objective: c.dot(&x), x, y })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    // Strong duality and dual feasibility A^T y >= c hold at every optimum.
+    fn assert_optimal(a:&Array2<f64>, b:&Array1<f64>, c:&Array1<f64>, solution:&LpSolution) {
+        assert!((b.dot(&solution.y) - solution.objective).abs() < 1e-9);
+        for (reduced, c) in a.t().dot(&solution.y).iter().zip(c) {
+            assert!(reduced - c > -1e-9);
+        }
+        assert!((a.dot(&solution.x) - b).iter().all(|v| v.abs() < 1e-9));
+        assert!(solution.x.iter().all(|v| *v >= -1e-12));
+    }
+
+    #[test]
+    fn textbook_problem() {
+        // max 3 x1 + 5 x2 s.t. x1 <= 4, 2 x2 <= 12, 3 x1 + 2 x2 <= 18, with slacks
+        let a = array![
+            [1., 0., 1., 0., 0.],
+            [0., 2., 0., 1., 0.],
+            [3., 2., 0., 0., 1.]
+        ];
+        let b = array![4., 12., 18.];
+        let c = array![3., 5., 0., 0., 0.];
+        let solution = maximize(&a, &b, &c).unwrap();
+        assert!((solution.objective - 36.).abs() < 1e-9);
+        assert!((solution.x[0] - 2.).abs() < 1e-9 && (solution.x[1] - 6.).abs() < 1e-9);
+        for (y, expected) in solution.y.iter().zip([0., 1.5, 1.]) {
+            assert!((y - expected).abs() < 1e-9);
+        }
+        assert_optimal(&a, &b, &c, &solution);
+    }
+
+    #[test]
+    fn negative_rhs_flips_the_duals_back() {
+        // max -x1 - 2 x2 s.t. -x1 - x2 = -2, so x1 = 2 and y = 1
+        let a = array![[-1., -1.]];
+        let b = array![-2.];
+        let c = array![-1., -2.];
+        let solution = maximize(&a, &b, &c).unwrap();
+        assert!((solution.objective + 2.).abs() < 1e-9);
+        assert!((solution.y[0] - 1.).abs() < 1e-9);
+        assert_optimal(&a, &b, &c, &solution);
+    }
+
+    #[test]
+    fn infeasible() {
+        let a = array![[1., 1.], [1., 1.]];
+        assert_eq!(maximize(&a, &array![1., 2.], &array![1., 1.]).err(), Some(LpError::Infeasible));
+        // x >= 0 cannot sum to a negative number
+        let a = array![[1., 1.]];
+        assert_eq!(maximize(&a, &array![-1.], &array![1., 1.]).err(), Some(LpError::Infeasible));
+    }
+
+    #[test]
+    fn unbounded() {
+        // max x1 s.t. x1 - x2 = 1
+        let a = array![[1., -1.]];
+        assert_eq!(maximize(&a, &array![1.], &array![1., 0.]).err(), Some(LpError::Unbounded));
+    }
+
+    #[test]
+    fn redundant_rows() {
+        // the second row repeats the first, its artificial stays in the basis at level 0
+        let a = array![[1., 1., 0.], [2., 2., 0.], [0., 1., 1.]];
+        let b = array![1., 2., 1.];
+        let c = array![1., 0., 0.];
+        let solution = maximize(&a, &b, &c).unwrap();
+        assert!((solution.objective - 1.).abs() < 1e-9);
+        assert!((a.dot(&solution.x) - &b).iter().all(|v| v.abs() < 1e-9));
+    }
+
+    #[test]
+    fn beale_cycling_example_terminates() {
+        // Degenerate problem on which the textbook pivoting rule cycles. The optimum is 5/4.
+        let a = array![
+            [0.25, -8., -1., 9., 1., 0., 0.],
+            [0.5, -12., -0.5, 3., 0., 1., 0.],
+            [0., 0., 1., 0., 0., 0., 1.]
+        ];
+        let b = array![0., 0., 1.];
+        let c = array![0.75, -20., 0.5, -6., 0., 0., 0.];
+        let solution = maximize(&a, &b, &c).unwrap();
+        assert!((solution.objective - 1.25).abs() < 1e-9);
+        assert_optimal(&a, &b, &c, &solution);
+    }
+}
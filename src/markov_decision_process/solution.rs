@@ -0,0 +1,218 @@
+//! What a solver returns. Every iterative solver counts its sweeps over the state
+//! space and gives up once SolverConfig's iteration or time budget is spent, e.g.
+//! for gamma = 1 without proper absorbing states, instead of looping forever.
+
+use std::{
+    fmt::Display,
+    time::{Duration, Instant}
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    backup::BackupScheme,
+    observer::{SolverObserver, SweepInfo, SweepSignal},
+    simplex::LpError,
+    validation::ModelError,
+    Action,
+    Policy
+};
+
+/// When value iteration (and the improvement step of modified policy iteration)
+/// stops, given the change v_{k+1} - v_k of the last sweep. The policy iteration
+/// solvers stop once their policy is stable, epsilon only sets how accurately each
+/// policy is evaluated.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StoppingRule {
+    #[default]
+    MAX_CHANGE, // max |v_{k+1} - v_k| < epsilon, no guarantee on the policy
+    // max |v_{k+1} - v_k| < epsilon * (1 - gamma) / (2 * gamma), then the greedy
+    // policy is epsilon-optimal and v_{k+1} is within epsilon / 2 of v*.
+    EPSILON_OPTIMAL,
+    // span(v_{k+1} - v_k) < epsilon * (1 - gamma) / gamma, then the greedy policy
+    // is epsilon-optimal. The span ignores a common shift of all values, so this
+    // usually stops well before EPSILON_OPTIMAL.
+    SPAN
+}
+
+impl StoppingRule {
+    /// Whether a sweep whose changes of the values lie in [lo, hi] ends the solve.
+    /// lo <= 0 <= hi, since terminal states never change.
+    pub fn is_met(&self, lo:f64, hi:f64, epsilon:f64, gamma:f64) -> bool {
+        match self {
+            StoppingRule::MAX_CHANGE => hi.max(-lo) < epsilon,
+            StoppingRule::EPSILON_OPTIMAL => hi.max(-lo) < epsilon * (1. - gamma) / (2. * gamma),
+            StoppingRule::SPAN => hi - lo < epsilon * (1. - gamma) / gamma,
+        }
+    }
+}
+
+/// How the returned policy picks among actions whose Q values are within
+/// tie_tolerance of the best one. Only the final policy of the discounted solvers
+/// is affected, the solver loops themselves are unchanged.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TieBreak {
+    #[default]
+    SOLVER, // keep the action the solver ended with
+    FIRST, // first in the order of get_actions_at_state
+    LOWEST_INDEX,
+    RANDOM(u64), // uniformly among the tied actions, seeded
+    PREFER_DEFAULT // the default action if it is tied for best, else the lowest index
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolverConfig {
+    pub max_iterations: usize, // sweeps over the state space, over all inner loops
+    pub timeout: Option<Duration>,
+    pub stopping: StoppingRule,
+    pub backup: BackupScheme, // of value_iteration, policy evaluation is always Gauss-Seidel
+    pub tie_break: TieBreak,
+    pub tie_tolerance: f64,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            max_iterations: 100_000,
+            timeout: None,
+            stopping: StoppingRule::default(),
+            backup: BackupScheme::default(),
+            tie_break: TieBreak::default(),
+            tie_tolerance: 1e-9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Solution {
+    pub policy: Policy,
+    pub values: Vec<f64>,
+    pub iterations: usize,
+    #[serde(with = "super::persistence::non_finite")]
+    pub residual: f64, // max |T(v) - v| over the last sweep, or of the final values
+    // Certified upper bound on max_s v*(s) - v_policy(s), see suboptimality_bound.
+    // Infinite for gamma = 1 and the average reward solvers.
+    #[serde(with = "super::persistence::non_finite")]
+    pub bound: f64,
+    pub elapsed: Duration,
+    // The observer returned SweepSignal::STOP, the solve may not have met its own
+    // stopping rule.
+    pub stopped_by_observer: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SolveError {
+    // A limit of SolverConfig was hit, carries the last iterate.
+    NotConverged(Solution),
+    // (I - gamma * P_pi) is singular or BiCGSTAB did not converge, or the average
+    // reward evaluation equations could not be solved.
+    Singular,
+    // The solver does not handle models with more than limit states, e.g. multichain
+    // policies in AVERAGE_POLICY_ITER.
+    TooLarge { states: usize, limit: usize },
+    // SOFT_VALUE_ITER needs a positive, finite temperature.
+    InvalidTemperature(#[serde(with = "super::persistence::non_finite")] f64),
+    // validate rejected the model, see solve_generic.
+    InvalidModel(Vec<ModelError>),
+    Lp(LpError),
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::NotConverged(partial) =>
+                write!(f, "not converged after {} iterations ({:?}), residual {}, suboptimality bound {}",
+                    partial.iterations, partial.elapsed, partial.residual, partial.bound),
+            SolveError::Singular =>
+                write!(f, "the policy evaluation equations could not be solved"),
+            SolveError::TooLarge { states, limit } =>
+                write!(f, "the model has {} states, this solver handles at most {}", states, limit),
+            SolveError::InvalidTemperature(temperature) =>
+                write!(f, "the temperature must be positive and finite, got {}", temperature),
+            SolveError::InvalidModel(errors) => {
+                write!(f, "the model is invalid:")?;
+                errors.iter().try_for_each(|e| write!(f, " {};", e))
+            },
+            SolveError::Lp(e) =>
+                write!(f, "the linear program is {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+impl From<LpError> for SolveError {
+    fn from(e: LpError) -> Self {
+        SolveError::Lp(e)
+    }
+}
+
+/// Sweep counter of a single solver run, which also reports every sweep to the observer.
+pub(crate) struct Sweeps<'a> {
+    start: Instant,
+    count: usize,
+    residual: f64,
+    max_iterations: usize,
+    timeout: Option<Duration>,
+    observer: &'a mut dyn SolverObserver,
+    stopped: bool,
+}
+
+impl <'a> Sweeps<'a> {
+
+    pub fn new(config:&SolverConfig, observer:&'a mut dyn SolverObserver) -> Self {
+        Sweeps {
+            start: Instant::now(),
+            count: 0,
+            residual: f64::INFINITY,
+            max_iterations: config.max_iterations,
+            timeout: config.timeout,
+            observer,
+            stopped: false,
+        }
+    }
+
+    /// Counts one sweep, returns false once a limit is hit or the observer stops.
+    pub fn tick(&mut self, values:&[f64], policy:&[Action]) -> bool {
+        self.count += 1;
+        let info = SweepInfo {
+            iteration: self.count,
+            residual: self.residual,
+            values,
+            policy,
+        };
+        self.stopped |= self.observer.after_sweep(&info) == SweepSignal::STOP;
+        !self.stopped
+            && self.count < self.max_iterations
+            && self.timeout.is_none_or(|t| self.start.elapsed() < t)
+    }
+
+    /// Like tick, for a sweep that measured the given residual.
+    pub fn record(&mut self, residual:f64, values:&[f64], policy:&[Action]) -> bool {
+        self.residual = residual;
+        self.tick(values, policy)
+    }
+
+    pub fn set_residual(&mut self, residual:f64) {
+        self.residual = residual;
+    }
+
+    pub fn finish(&self, policy:Policy, values:Vec<f64>, bound:f64, converged:bool) -> Result<Solution, SolveError> {
+        let solution = Solution {
+            policy,
+            values,
+            iterations: self.count,
+            residual: self.residual,
+            bound,
+            elapsed: self.start.elapsed(),
+            stopped_by_observer: self.stopped,
+        };
+        if converged || self.stopped {
+            Ok(solution)
+        } else {
+            Err(SolveError::NotConverged(solution))
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Stochastic policies and entropy-regularised ("soft") planning. Soft value
+//! iteration replaces the max of the Bellman backup by a log-sum-exp with a
+//! temperature tau,
+//!   V(s) = tau * log sum_a exp(Q(s, a) / tau),  Q(s, a) = sum p * (r + gamma * V(s')),
+//! and its policy is the Boltzmann distribution pi(a|s) = exp((Q(s, a) - V(s)) / tau).
+//! As tau -> 0 this is value iteration with a greedy policy.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    observer::SolverObserver,
+    solution::Sweeps,
+    Action,
+    MarkovDecisionProcess,
+    Policy,
+    Solution,
+    SolveError,
+    State,
+    StateSpace
+};
+
+/// A distribution over the actions of every state. Terminal states and states
+/// without actions have an empty distribution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StochasticPolicy {
+    distributions: Vec<Vec<(Action, f64)>>, // per state, (a, pi(a|s))
+}
+
+impl StochasticPolicy {
+
+    /// Probabilities of every state are normalised to sum to 1.
+    pub fn new(distributions:Vec<Vec<(Action, f64)>>) -> Self {
+        let distributions = distributions.into_iter()
+        .map(|row| {
+            let total:f64 = row.iter().map(|(_, p)| p).sum();
+            row.into_iter().map(|(a, p)| (a, p / total)).collect()
+        }).collect();
+        StochasticPolicy { distributions }
+    }
+
+    /// The deterministic policy pi as a distribution, skipping states without actions.
+    pub fn from_policy<S:StateSpace>(state_space:&S, pi:&Policy) -> Self {
+        let distributions = state_space.get_all_states()
+        .map(|s| {
+            if state_space.is_terminal_state(s) || state_space.get_actions_at_state(s).is_empty() {
+                Vec::new()
+            } else {
+                vec![(pi[*s], 1.)]
+            }
+        }).collect();
+        StochasticPolicy { distributions }
+    }
+
+    pub fn len(&self) -> usize {
+        self.distributions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distributions.is_empty()
+    }
+
+    pub fn distribution(&self, s:&State) -> &[(Action, f64)] {
+        &self.distributions[*s]
+    }
+
+    pub fn probability(&self, s:&State, a:&Action) -> f64 {
+        self.distributions[*s].iter()
+            .filter(|(b, _)| b == a)
+            .map(|(_, p)| p)
+            .sum()
+    }
+
+    /// Samples an action at s, None if s has no actions.
+    pub fn sample<R:Rng>(&self, s:&State, rng:&mut R) -> Option<Action> {
+        let row:&[(Action, f64)] = &self.distributions[*s];
+        let mut u:f64 = rng.gen::<f64>();
+        for (a, p) in row {
+            if u < *p {
+                return Some(*a)
+            }
+            u -= p;
+        }
+        // round-off
+        row.iter().rev().find(|(_, p)| *p > 0.).map(|(a, _)| *a)
+    }
+
+    /// The most likely action of every state, first one wins ties. States without
+    /// actions get default_action.
+    pub fn mode(&self, default_action:Action) -> Policy {
+        self.distributions.iter()
+        .map(|row| {
+            row.iter()
+            .fold((f64::MIN, default_action), |acc, (a, p)| if *p > acc.0 { (*p, *a) } else { acc })
+            .1
+        }).collect()
+    }
+}
+
+impl <S: StateSpace + std::marker::Sync> MarkovDecisionProcess<S> {
+
+    fn soft_backup(&self, temperature:f64, current_values:&[f64], s:&State) -> (f64, Action) {
+        // (tau * log sum_a exp(Q(s, a) / tau), argmax_a Q(s, a)), shifted by the max for stability
+        let q:Vec<(Action, f64)> = self.state_space.get_actions_at_state(s)
+            .into_iter()
+            .map(|a| (a, self.q(current_values, s, &a)))
+            .collect();
+        let (best_q, best_a):(f64, Action) = q.iter()
+            .fold((f64::MIN, self.default_action), |acc, (a, v)| if *v > acc.0 { (*v, *a) } else { acc });
+        let total:f64 = q.iter().map(|(_, v)| ((v - best_q) / temperature).exp()).sum();
+        (best_q + temperature * total.ln(), best_a)
+    }
+
+    /// Softmax of Q(s, .) / temperature under the learned values.
+    pub fn boltzmann_policy(&self, temperature:f64) -> StochasticPolicy {
+        let distributions = self.get_q_table()
+        .into_iter()
+        .map(|row| {
+            let best_q:f64 = row.iter().fold(f64::MIN, |acc, (_, q)| acc.max(*q));
+            row.into_iter().map(|(a, q)| (a, ((q - best_q) / temperature).exp())).collect()
+        }).collect();
+        StochasticPolicy::new(distributions)
+    }
+
+    pub fn soft_value_iteration(&mut self, epsilon:f64, temperature:f64) -> Result<(Solution, StochasticPolicy), SolveError> {
+        self.soft_value_iteration_with(epsilon, temperature, &mut ())
+    }
+
+    pub(crate) fn soft_value_iteration_with(&mut self, epsilon:f64, temperature:f64, observer:&mut dyn SolverObserver) -> Result<(Solution, StochasticPolicy), SolveError> {
+        // Jacobi sweeps of the soft Bellman backup, which is a gamma-contraction as well,
+        // so the stopping rules apply to the soft values. States without actions keep
+        // their value. The Solution carries the soft values and the most likely action
+        // of the Boltzmann policy, its bound is measured against the ordinary optimum.
+        if !(temperature > 0. && temperature.is_finite()) {
+            return Err(SolveError::InvalidTemperature(temperature))
+        }
+        self.reset_values();
+        let mut sweeps = Sweeps::new(&self.config, observer);
+        let converged:bool = loop {
+            let (new_values, greedy):(Vec<f64>, Policy) = self.state_space.get_all_states()
+            .map(|s| {
+                if self.state_space.is_terminal_state(s) || self.state_space.get_actions_at_state(s).is_empty() {
+                    (self.learned_values[*s], self.default_action)
+                } else {
+                    self.soft_backup(temperature, &self.learned_values, s)
+                }
+            }).unzip();
+
+            let (lo, hi):(f64, f64) = new_values.iter().zip(&self.learned_values)
+                .fold((0., 0.), |(lo, hi), (new, old)| (lo.min(new - old), hi.max(new - old)));
+            self.learned_values = new_values;
+            let within_limits:bool = sweeps.record(hi.max(-lo), &self.learned_values, &greedy);
+            if self.config.stopping.is_met(lo, hi, epsilon, self.gamma) {
+                break true
+            }
+            if !within_limits {
+                break false
+            }
+        };
+
+        let boltzmann:StochasticPolicy = self.boltzmann_policy(temperature);
+        let solution:Solution = self.finish(sweeps, boltzmann.mode(self.default_action), converged)?;
+        Ok((solution, boltzmann))
+    }
+}
@@ -0,0 +1,260 @@
+//! An explicit MDP stored in a compressed sparse row layout. It can be built
+//! transition by transition, or copied once from any StateSpace so that the
+//! solvers read stored transitions instead of recomputing them every sweep.
+
+use std::{collections::BTreeMap, slice::Iter};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Action,
+    State,
+    StateSpace
+};
+
+type Transitions = Vec<(State, f64, f64)>; // (next state, prob, reward)
+type Rows = Vec<Vec<(Action, Transitions)>>; // per state, every action with its transitions
+
+// Serialized as its rows, which is more readable than the flat arrays and
+// cannot describe inconsistent pointers.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "TabularRows", into = "TabularRows")]
+pub struct TabularMdp {
+    // actions of state s are actions[state_ptr[s]..state_ptr[s + 1]]
+    state_ptr: Vec<usize>,
+    actions: Vec<Action>,
+    // transitions of the k-th (state, action) pair are at action_ptr[k]..action_ptr[k + 1]
+    action_ptr: Vec<usize>,
+    next_states: Vec<State>,
+    probs: Vec<f64>,
+    rewards: Vec<f64>,
+    terminal: Vec<bool>,
+    all_states: Vec<State>,
+}
+
+impl TabularMdp {
+
+    pub fn builder(n_states:usize) -> TabularMdpBuilder {
+        TabularMdpBuilder::new(n_states)
+    }
+
+    /// Enumerates get_all_states and get_future_rewards of state_space once.
+    /// Actions keep the order of get_actions_at_state.
+    pub fn from_state_space<S:StateSpace>(state_space:&S) -> Self {
+        let n:usize = state_space.len();
+        let mut rows:Rows = vec![Vec::new(); n];
+        let mut terminal:Vec<bool> = vec![false; n];
+        for s in state_space.get_all_states() {
+            terminal[*s] = state_space.is_terminal_state(s);
+            rows[*s] = state_space.get_actions_at_state(s)
+                .into_iter()
+                .map(|a| (a, state_space.get_future_rewards(s, &a)))
+                .collect();
+        }
+        TabularMdp::from_rows(rows, terminal)
+    }
+
+    fn from_rows(rows:Rows, terminal:Vec<bool>) -> Self {
+        let mut mdp = TabularMdp {
+            state_ptr: vec![0],
+            actions: Vec::new(),
+            action_ptr: vec![0],
+            next_states: Vec::new(),
+            probs: Vec::new(),
+            rewards: Vec::new(),
+            all_states: (0..rows.len()).collect(),
+            terminal,
+        };
+        for row in rows {
+            for (a, transitions) in row {
+                mdp.actions.push(a);
+                for (next, p, r) in transitions {
+                    mdp.next_states.push(next);
+                    mdp.probs.push(p);
+                    mdp.rewards.push(r);
+                }
+                mdp.action_ptr.push(mdp.next_states.len());
+            }
+            mdp.state_ptr.push(mdp.actions.len());
+        }
+        mdp
+    }
+
+    fn to_rows(&self) -> Rows {
+        self.all_states.iter()
+        .map(|s| {
+            (self.state_ptr[*s]..self.state_ptr[s + 1])
+            .map(|k| {
+                let transitions:Transitions = (self.action_ptr[k]..self.action_ptr[k + 1])
+                    .map(|t| (self.next_states[t], self.probs[t], self.rewards[t]))
+                    .collect();
+                (self.actions[k], transitions)
+            }).collect()
+        }).collect()
+    }
+
+    /// Number of stored (state, action, next state) transitions.
+    pub fn n_transitions(&self) -> usize {
+        self.next_states.len()
+    }
+
+    fn pair_index(&self, s:&State, a:&Action) -> Option<usize> {
+        let start:usize = self.state_ptr[*s];
+        self.actions[start..self.state_ptr[s + 1]]
+            .iter()
+            .position(|x| x == a)
+            .map(|k| start + k)
+    }
+}
+
+impl StateSpace for TabularMdp {
+
+    fn get_actions_at_state(&self, s:&State) -> Vec<Action> {
+        self.actions[self.state_ptr[*s]..self.state_ptr[s + 1]].to_vec()
+    }
+
+    fn get_future_rewards(&self, s:&State, a:&Action) -> Vec<(State, f64, f64)> {
+        match self.pair_index(s, a) {
+            Some(k) => (self.action_ptr[k]..self.action_ptr[k + 1])
+                .map(|t| (self.next_states[t], self.probs[t], self.rewards[t]))
+                .collect(),
+            None => Vec::new()
+        }
+    }
+
+    fn get_all_states(&self) -> Iter<'_, State> {
+        self.all_states.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.all_states.len()
+    }
+
+    fn is_terminal_state(&self, s:&State) -> bool {
+        self.terminal[*s]
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TabularRows {
+    rows: Rows,
+    terminal: Vec<bool>,
+}
+
+impl From<TabularMdp> for TabularRows {
+    fn from(mdp: TabularMdp) -> Self {
+        TabularRows { rows: mdp.to_rows(), terminal: mdp.terminal }
+    }
+}
+
+impl TryFrom<TabularRows> for TabularMdp {
+    type Error = String;
+
+    // Only what would make the StateSpace methods panic is rejected here, see
+    // validate for the other checks of a model.
+    fn try_from(data: TabularRows) -> Result<Self, Self::Error> {
+        let n:usize = data.rows.len();
+        if data.terminal.len() != n {
+            return Err(format!("{} terminal flags for {} states", data.terminal.len(), n))
+        }
+        let out_of_range = data.rows.iter()
+            .flat_map(|row| row.iter().flat_map(|(_, transitions)| transitions.iter()))
+            .find(|(next, _, _)| *next >= n);
+        if let Some((next, _, _)) = out_of_range {
+            return Err(format!("next state {} is out of range for {} states", next, n))
+        }
+        Ok(TabularMdp::from_rows(data.rows, data.terminal))
+    }
+}
+
+pub struct TabularMdpBuilder {
+    n_states: usize,
+    transitions: BTreeMap<(State, Action), Transitions>,
+    terminal: Vec<bool>,
+}
+
+impl TabularMdpBuilder {
+
+    pub fn new(n_states:usize) -> Self {
+        TabularMdpBuilder {
+            n_states,
+            transitions: BTreeMap::new(),
+            terminal: vec![false; n_states],
+        }
+    }
+
+    /// Adds s --a--> next with probability p and reward r. The action becomes
+    /// available at s. Panics if a state is out of range.
+    pub fn add_transition(&mut self, s:State, a:Action, next:State, p:f64, r:f64) -> &mut Self {
+        assert!(s < self.n_states && next < self.n_states, "State out of range.");
+        self.transitions.entry((s, a)).or_default().push((next, p, r));
+        self
+    }
+
+    /// Panics if s is out of range.
+    pub fn mark_terminal(&mut self, s:State) -> &mut Self {
+        assert!(s < self.n_states, "State out of range.");
+        self.terminal[s] = true;
+        self
+    }
+
+    /// Actions at every state are sorted by their index.
+    pub fn build(&self) -> TabularMdp {
+        let mut rows:Rows = vec![Vec::new(); self.n_states];
+        for ((s, a), transitions) in &self.transitions {
+            rows[*s].push((*a, transitions.clone()));
+        }
+        TabularMdp::from_rows(rows, self.terminal.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::grid_world::GridWorld;
+
+    #[test]
+    fn builder_stores_the_transitions() {
+        let mut builder = TabularMdp::builder(3);
+        builder
+            .add_transition(0, 4, 1, 0.5, 1.)
+            .add_transition(0, 4, 2, 0.5, -1.)
+            .add_transition(0, 1, 2, 1., 3.)
+            .add_transition(1, 0, 2, 1., 0.)
+            .mark_terminal(2);
+        let mdp:TabularMdp = builder.build();
+        assert_eq!(mdp.len(), 3);
+        assert_eq!(mdp.n_transitions(), 4);
+        assert_eq!(mdp.get_actions_at_state(&0), vec![1, 4]);
+        assert_eq!(mdp.get_future_rewards(&0, &4), vec![(1, 0.5, 1.), (2, 0.5, -1.)]);
+        assert!(mdp.get_future_rewards(&0, &0).is_empty());
+        assert!(mdp.get_actions_at_state(&2).is_empty());
+        assert!(!mdp.is_terminal_state(&1) && mdp.is_terminal_state(&2));
+    }
+
+    #[test]
+    fn copies_of_a_state_space_agree_with_it() {
+        let grid = GridWorld::default();
+        let mdp = TabularMdp::from_state_space(&grid);
+        assert_eq!(mdp.len(), StateSpace::len(&grid));
+        for s in StateSpace::get_all_states(&grid) {
+            assert_eq!(mdp.is_terminal_state(s), StateSpace::is_terminal_state(&grid, s));
+            assert_eq!(mdp.get_actions_at_state(s), StateSpace::get_actions_at_state(&grid, s));
+            for a in mdp.get_actions_at_state(s) {
+                assert_eq!(mdp.get_future_rewards(s, &a), StateSpace::get_future_rewards(&grid, s, &a));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "State out of range.")]
+    fn add_transition_rejects_unknown_states() {
+        TabularMdp::builder(2).add_transition(0, 0, 2, 1., 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "State out of range.")]
+    fn mark_terminal_rejects_unknown_states() {
+        TabularMdp::builder(2).mark_terminal(2);
+    }
+}
@@ -0,0 +1,205 @@
+//! Sanity checks of a StateSpace before it is handed to a solver. The solvers
+//! index value vectors by state, so a model that breaks these assumptions either
+//! panics or silently produces garbage.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Action,
+    State,
+    StateSpace
+};
+
+// Allowed deviation of the total probability of an action from 1.
+const PROBABILITY_TOL: f64 = 1e-9;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ModelError {
+    // get_all_states does not list exactly len() states.
+    StateCountMismatch { len: usize, listed: usize },
+    // get_all_states must list state i at position i.
+    StateOrder { position: usize, state: State },
+    BadProbabilityMass {
+        state: State,
+        action: Action,
+        #[serde(with = "super::persistence::non_finite")]
+        total: f64
+    },
+    NegativeProbability {
+        state: State,
+        action: Action,
+        next: State,
+        #[serde(with = "super::persistence::non_finite")]
+        prob: f64
+    },
+    NextStateOutOfRange { state: State, action: Action, next: State },
+    NanReward { state: State, action: Action, next: State },
+    TerminalWithActions { state: State, actions: Vec<Action> },
+}
+
+impl Display for ModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelError::StateCountMismatch { len, listed } =>
+                write!(f, "len() is {} but get_all_states lists {} states", len, listed),
+            ModelError::StateOrder { position, state } =>
+                write!(f, "state {} is listed at position {}", state, position),
+            ModelError::BadProbabilityMass { state, action, total } =>
+                write!(f, "probabilities of action {} at state {} sum to {}", action, state, total),
+            ModelError::NegativeProbability { state, action, next, prob } =>
+                write!(f, "action {} at state {} goes to {} with probability {}", action, state, next, prob),
+            ModelError::NextStateOutOfRange { state, action, next } =>
+                write!(f, "action {} at state {} goes to state {}, which is out of range", action, state, next),
+            ModelError::NanReward { state, action, next } =>
+                write!(f, "action {} at state {} has a NaN reward going to {}", action, state, next),
+            ModelError::TerminalWithActions { state, actions } =>
+                write!(f, "terminal state {} has actions {:?}", state, actions),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+/// Checks the whole model and returns every violation found.
+pub fn validate<S:StateSpace>(state_space:&S) -> Result<(), Vec<ModelError>> {
+    let n:usize = state_space.len();
+    let mut errors:Vec<ModelError> = Vec::new();
+
+    let listed:usize = state_space.get_all_states().count();
+    if listed != n {
+        errors.push(ModelError::StateCountMismatch { len: n, listed });
+    }
+    for (position, s) in state_space.get_all_states().enumerate() {
+        if *s != position {
+            errors.push(ModelError::StateOrder { position, state: *s });
+        }
+    }
+
+    for s in state_space.get_all_states() {
+        let actions:Vec<Action> = state_space.get_actions_at_state(s);
+        if state_space.is_terminal_state(s) {
+            if !actions.is_empty() {
+                errors.push(ModelError::TerminalWithActions { state: *s, actions });
+            }
+            continue
+        }
+        for a in actions {
+            let mut total:f64 = 0.;
+            for (next, p, r) in state_space.get_future_rewards(s, &a) {
+                total += p;
+                if p < 0. {
+                    errors.push(ModelError::NegativeProbability { state: *s, action: a, next, prob: p });
+                }
+                if next >= n {
+                    errors.push(ModelError::NextStateOutOfRange { state: *s, action: a, next });
+                }
+                if r.is_nan() {
+                    errors.push(ModelError::NanReward { state: *s, action: a, next });
+                }
+            }
+            if total.is_nan() || (total - 1.).abs() > PROBABILITY_TOL {
+                errors.push(ModelError::BadProbabilityMass { state: *s, action: a, total });
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice::Iter;
+
+    use super::*;
+    use crate::{
+        examples::{dien::DieN, grid_world::GridWorld},
+        markov_decision_process::TabularMdp
+    };
+
+    // A TabularMdp that lists its states as given and can leak out of range.
+    struct Listed {
+        inner: TabularMdp,
+        listed: Vec<State>,
+        leak: bool,
+    }
+
+    impl StateSpace for Listed {
+        fn get_actions_at_state(&self, s:&State) -> Vec<Action> {
+            self.inner.get_actions_at_state(s)
+        }
+
+        fn get_future_rewards(&self, s:&State, a:&Action) -> Vec<(State, f64, f64)> {
+            let mut transitions = self.inner.get_future_rewards(s, a);
+            if self.leak && *s == 0 {
+                transitions.push((9, 0., 0.));
+            }
+            transitions
+        }
+
+        fn get_all_states(&self) -> Iter<'_, State> {
+            self.listed.iter()
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+
+        fn is_terminal_state(&self, s:&State) -> bool {
+            self.inner.is_terminal_state(s)
+        }
+    }
+
+    fn two_states() -> TabularMdp {
+        let mut builder = TabularMdp::builder(2);
+        builder.add_transition(0, 0, 1, 1., 1.).mark_terminal(1);
+        builder.build()
+    }
+
+    #[test]
+    fn examples_are_valid() {
+        assert!(validate(&GridWorld::default()).is_ok());
+        assert!(validate(&GridWorld::with_size(7, 3)).is_ok());
+        for config in [vec![1,1,1,0,0,0], vec![0,1,0,1,0,0,1,0], vec![1,0,0,0]] {
+            let die = DieN::new(config);
+            assert_eq!(die.get_all_states().count(), die.len());
+            assert!(validate(&die).is_ok());
+        }
+    }
+
+    #[test]
+    fn transition_errors_are_reported() {
+        let mut builder = TabularMdp::builder(3);
+        builder
+            .add_transition(0, 0, 1, 0.5, 0.) // mass 0.5
+            .add_transition(0, 1, 1, -0.5, 0.) // mass 1, but negative
+            .add_transition(0, 1, 2, 1.5, 0.)
+            .add_transition(1, 0, 2, 1., f64::NAN)
+            .add_transition(2, 3, 2, 1., 0.)
+            .mark_terminal(2);
+        let errors:Vec<ModelError> = validate(&builder.build()).unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert_eq!(errors[0], ModelError::BadProbabilityMass { state: 0, action: 0, total: 0.5 });
+        assert_eq!(errors[1], ModelError::NegativeProbability { state: 0, action: 1, next: 1, prob: -0.5 });
+        assert_eq!(errors[2], ModelError::NanReward { state: 1, action: 0, next: 2 });
+        assert_eq!(errors[3], ModelError::TerminalWithActions { state: 2, actions: vec![3] });
+    }
+
+    #[test]
+    fn state_listing_errors_are_reported() {
+        let reordered = Listed { inner: two_states(), listed: vec![1, 0], leak: false };
+        assert_eq!(validate(&reordered).unwrap_err(), vec![
+            ModelError::StateOrder { position: 0, state: 1 },
+            ModelError::StateOrder { position: 1, state: 0 },
+        ]);
+        let short = Listed { inner: two_states(), listed: vec![0], leak: false };
+        assert_eq!(validate(&short).unwrap_err(), vec![ModelError::StateCountMismatch { len: 2, listed: 1 }]);
+        let leaking = Listed { inner: two_states(), listed: vec![0, 1], leak: true };
+        assert_eq!(validate(&leaking).unwrap_err(), vec![ModelError::NextStateOutOfRange { state: 0, action: 0, next: 9 }]);
+    }
+}
@@ -0,0 +1,210 @@
+//! Dyna-Q: Q-learning from real experience, plus planning updates from a
+//! tabular model of the environment learned from that same experience. The
+//! learned model implements StateSpace, so it can be handed to
+//! MarkovDecisionProcess for full planning.
+
+use rand::Rng;
+use std::collections::BTreeMap;
+
+use crate::markov_decision_process::{
+    Action,
+    Policy,
+    State,
+    StateSpace
+};
+use super::{
+    run_episodes,
+    sample_transition,
+    Agent,
+    AgentConfig,
+    AgentCore
+};
+
+type Outcomes = BTreeMap<State, (usize, f64)>; // next state -> (count, reward sum)
+
+/// Maximum likelihood model of the observed transitions. Only actions that have
+/// been tried at a state are available there. Probabilities are the observed
+/// frequencies and rewards the mean observed reward per (s, a, s'). States that
+/// were never acted from are reported as terminal, so that the planners treat
+/// them as absorbing with value 0.
+#[derive(Clone)]
+pub struct LearnedModel {
+    transitions: Vec<BTreeMap<Action, Outcomes>>, // per state
+    terminal: Vec<bool>,
+    all_states: Vec<State>,
+}
+
+impl LearnedModel {
+    /// An empty model over the states of state_space, which also provides the terminal states.
+    pub fn new<S:StateSpace>(state_space:&S) -> Self {
+        let n:usize = state_space.len();
+        let mut terminal:Vec<bool> = vec![false; n];
+        for s in state_space.get_all_states() {
+            terminal[*s] = state_space.is_terminal_state(s);
+        }
+        LearnedModel {
+            transitions: vec![BTreeMap::new(); n],
+            terminal,
+            all_states: (0..n).collect()
+        }
+    }
+
+    pub fn observe(&mut self, s:&State, a:&Action, next:&State, r:f64) {
+        let entry = self.transitions[*s]
+            .entry(*a).or_default()
+            .entry(*next).or_insert((0, 0.));
+        entry.0 += 1;
+        entry.1 += r;
+    }
+
+    /// Samples (next state, reward) from the observed transitions of (s, a).
+    pub fn sample<R:Rng>(&self, s:&State, a:&Action, rng:&mut R) -> Option<(State, f64)> {
+        let outcomes = self.transitions[*s].get(a)?;
+        let total:usize = outcomes.values().map(|(c, _)| c).sum();
+        let mut u:usize = rng.gen_range(0..total);
+        for (next, (count, reward_sum)) in outcomes {
+            if u < *count {
+                return Some((*next, reward_sum / *count as f64))
+            }
+            u -= count;
+        }
+        None
+    }
+}
+
+impl StateSpace for LearnedModel {
+    fn get_actions_at_state(&self, s:&State) -> Vec<Action> {
+        self.transitions[*s].keys().copied().collect()
+    }
+
+    fn get_future_rewards(&self, s:&State, a:&Action) -> Vec<(State, f64, f64)> {
+        match self.transitions[*s].get(a) {
+            Some(outcomes) => {
+                let total:f64 = outcomes.values().map(|(c, _)| *c as f64).sum();
+                outcomes.iter()
+                    .map(|(next, (count, reward_sum))| (*next, *count as f64 / total, reward_sum / *count as f64))
+                    .collect()
+            }
+            None => Vec::new()
+        }
+    }
+
+    fn get_all_states(&self) -> std::slice::Iter<'_, State> {
+        self.all_states.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.all_states.len()
+    }
+
+    fn is_terminal_state(&self, s:&State) -> bool {
+        self.terminal[*s] || self.transitions[*s].is_empty()
+    }
+}
+
+pub struct DynaQ<S: StateSpace> {
+    core: AgentCore<S>,
+    planning_steps: usize, // simulated updates after every real step
+    model: LearnedModel,
+}
+
+impl <S: StateSpace> DynaQ<S> {
+
+    pub fn new(state_space:S, default_action:Action, config:AgentConfig, planning_steps:usize) -> DynaQ<S> {
+        let model = LearnedModel::new(&state_space);
+        DynaQ {
+            core: AgentCore::new(state_space, default_action, config),
+            planning_steps,
+            model
+        }
+    }
+
+    pub fn get_model(&self) -> &LearnedModel {
+        &self.model
+    }
+
+    pub fn into_model(self) -> LearnedModel {
+        self.model
+    }
+}
+
+impl <S: StateSpace> Agent for DynaQ<S> {
+    type Space = S;
+
+    fn core(&self) -> &AgentCore<S> {
+        &self.core
+    }
+
+    fn learn(&mut self) -> Policy {
+        let (mut rng, mut q) = self.core.start();
+        let mut model = LearnedModel::new(&self.core.state_space);
+        // (s, index of a) pairs seen so far, planning starts from these
+        let mut observed:Vec<(State, usize)> = Vec::new();
+        let state_space = &self.core.state_space;
+        let config = &self.core.config;
+        let planning_steps = self.planning_steps;
+        let episode_returns = run_episodes(state_space, config, &mut rng, |start, epsilon, rng| {
+            let mut s:State = start;
+            let mut episode_return:f64 = 0.;
+            for _ in 0..config.max_steps {
+                if q.is_absorbing(&s) {
+                    break
+                }
+                let i:usize = q.epsilon_greedy(&s, epsilon, rng);
+                let a:Action = q.actions(&s)[i];
+                let (next, r) = match sample_transition(state_space, &s, &a, rng) {
+                    Some(t) => t,
+                    None => break,
+                };
+                q.update(&s, i, r + config.gamma * q.max_q(&next), &config.learning_rate);
+                if model.get_future_rewards(&s, &a).is_empty() {
+                    observed.push((s, i));
+                }
+                model.observe(&s, &a, &next, r);
+
+                for _ in 0..planning_steps {
+                    let (ps, pi) = observed[rng.gen_range(0..observed.len())];
+                    let pa:Action = q.actions(&ps)[pi];
+                    if let Some((p_next, p_r)) = model.sample(&ps, &pa, rng) {
+                        q.update(&ps, pi, p_r + config.gamma * q.max_q(&p_next), &config.learning_rate);
+                    }
+                }
+                episode_return += r;
+                s = next;
+            }
+            episode_return
+        });
+        self.model = model;
+        self.core.finish(q, episode_returns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::grid_world::GridWorld,
+        markov_decision_process::{MDPSolver, MarkovDecisionProcess}
+    };
+
+    #[test]
+    fn partially_explored_model_can_be_solved() {
+        let config = AgentConfig { episodes: 2, ..AgentConfig::default() };
+        let mut agent = DynaQ::new(GridWorld::default(), 0, config, 5);
+        agent.learn();
+        let model:LearnedModel = agent.into_model();
+        let unexplored:Vec<State> = model.get_all_states()
+            .filter(|s| model.get_actions_at_state(s).is_empty())
+            .copied()
+            .collect();
+        assert!(!unexplored.is_empty(), "two episodes should not reach every state");
+        assert!(unexplored.iter().all(|s| model.is_terminal_state(s)));
+
+        let mut mdp = MarkovDecisionProcess::new(model, 0, 0.9);
+        assert!(mdp.validate().is_ok());
+        let solution = mdp.solve(MDPSolver::VALUE_ITER, 1e-9).unwrap();
+        // rewards lie in [-1, 1], so every value is within 1 / (1 - gamma)
+        assert!(solution.values.iter().all(|v| v.abs() <= 10.));
+        assert!(unexplored.iter().all(|s| solution.values[*s] == 0.));
+    }
+}
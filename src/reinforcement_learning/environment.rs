@@ -0,0 +1,187 @@
+//! Gym-style episodic environments. Simulator turns any StateSpace into one by
+//! sampling the transitions of get_future_rewards, so the examples (GridWorld,
+//! DieN) can be used as benchmarks for code that only talks to an Environment.
+//! rollout evaluates policies through an Environment. The learning agents of this
+//! module do not, they still sample the StateSpace directly with sample_transition.
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::markov_decision_process::{
+    Action,
+    State,
+    StateSpace
+};
+use super::{
+    sample_start_from,
+    sample_transition
+};
+
+pub trait Environment {
+    /// Starts a new episode and returns its start state.
+    fn reset(&mut self) -> State;
+    /// Takes a in the current state, returns (next state, reward, done).
+    fn step(&mut self, a:Action) -> (State, f64, bool);
+    /// The actions available in the current state, empty once the episode is done.
+    fn actions(&self) -> Vec<Action>;
+}
+
+/// A seeded simulator of a StateSpace. An episode ends in a terminal state, in a
+/// state without actions, when an action leads nowhere, or after max_steps steps.
+pub struct Simulator<S: StateSpace> {
+    state_space: S,
+    start_states: Vec<State>, // empty means a uniformly random non-terminal state
+    max_steps: usize,
+    rng: StdRng,
+    state: State,
+    steps: usize,
+    done: bool,
+}
+
+impl <S: StateSpace> Simulator<S> {
+
+    /// Like in gym, the first episode begins with the first call to reset.
+    pub fn new(state_space:S, start_states:Vec<State>, max_steps:usize, seed:u64) -> Self {
+        Simulator {
+            state_space,
+            start_states,
+            max_steps,
+            rng: StdRng::seed_from_u64(seed),
+            state: 0,
+            steps: 0,
+            done: true,
+        }
+    }
+
+    /// Restarts the random number generator, the next reset begins the same sequence
+    /// of episodes as a new Simulator with this seed.
+    pub fn seed(&mut self, seed:u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn get_state(&self) -> State {
+        self.state
+    }
+
+    pub fn get_steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn get_state_space(&self) -> &S {
+        &self.state_space
+    }
+
+    fn is_absorbing(&self, s:&State) -> bool {
+        self.state_space.is_terminal_state(s) || self.state_space.get_actions_at_state(s).is_empty()
+    }
+}
+
+impl <S: StateSpace> Environment for Simulator<S> {
+
+    fn reset(&mut self) -> State {
+        self.state = sample_start_from(&self.state_space, &self.start_states, &mut self.rng);
+        self.steps = 0;
+        self.done = self.is_absorbing(&self.state);
+        self.state
+    }
+
+    fn step(&mut self, a:Action) -> (State, f64, bool) {
+        // Stepping a finished episode stays where it is, without reward.
+        if self.done {
+            return (self.state, 0., true)
+        }
+        self.steps += 1;
+        match sample_transition(&self.state_space, &self.state, &a, &mut self.rng) {
+            Some((next, r)) => {
+                self.state = next;
+                self.done = self.is_absorbing(&next) || self.steps >= self.max_steps;
+                (next, r, self.done)
+            },
+            None => {
+                self.done = true;
+                (self.state, 0., true)
+            }
+        }
+    }
+
+    fn actions(&self) -> Vec<Action> {
+        if self.done {
+            Vec::new()
+        } else {
+            self.state_space.get_actions_at_state(&self.state)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::dien::DieN,
+        markov_decision_process::TabularMdp
+    };
+
+    // 0 -> 1 -> 2 (terminal) with rewards 1 and 2, and a self loop at 3
+    fn chain() -> TabularMdp {
+        let mut builder = TabularMdp::builder(4);
+        builder
+            .add_transition(0, 0, 1, 1., 1.)
+            .add_transition(1, 0, 2, 1., 2.)
+            .add_transition(3, 0, 3, 1., 5.)
+            .mark_terminal(2);
+        builder.build()
+    }
+
+    #[test]
+    fn episodes_run_from_reset_to_a_terminal_state() {
+        let mut env = Simulator::new(chain(), vec![0], 100, 1);
+        assert!(env.is_done() && env.actions().is_empty());
+        assert_eq!(env.reset(), 0);
+        assert_eq!(env.actions(), vec![0]);
+        assert_eq!(env.step(0), (1, 1., false));
+        assert_eq!(env.step(0), (2, 2., true));
+        assert_eq!(env.get_steps(), 2);
+        assert!(env.actions().is_empty());
+        // a finished episode stays put
+        assert_eq!(env.step(0), (2, 0., true));
+        assert_eq!(env.get_steps(), 2);
+        // an unavailable action ends the episode
+        env.reset();
+        assert_eq!(env.step(7), (0, 0., true));
+    }
+
+    #[test]
+    fn episodes_are_cut_off_after_max_steps() {
+        let mut env = Simulator::new(chain(), vec![3], 3, 1);
+        env.reset();
+        assert_eq!(env.step(0), (3, 5., false));
+        assert_eq!(env.step(0), (3, 5., false));
+        assert_eq!(env.step(0), (3, 5., true));
+        assert!(env.is_done());
+    }
+
+    fn play<E:Environment>(env:&mut E, episodes:usize) -> Vec<(State, f64)> {
+        let mut trace:Vec<(State, f64)> = Vec::new();
+        for _ in 0..episodes {
+            trace.push((env.reset(), 0.));
+            while !env.actions().is_empty() {
+                let (next, r, _) = env.step(1); // always roll
+                trace.push((next, r));
+            }
+        }
+        trace
+    }
+
+    #[test]
+    fn seeding_reproduces_the_episodes() {
+        let mut env = Simulator::new(DieN::new(vec![1,1,1,0,0,0]), Vec::new(), 100, 9);
+        let first:Vec<(State, f64)> = play(&mut env, 20);
+        assert_eq!(first, play(&mut Simulator::new(DieN::new(vec![1,1,1,0,0,0]), Vec::new(), 100, 9), 20));
+        assert_ne!(first, play(&mut env, 20));
+        env.seed(9);
+        assert_eq!(first, play(&mut env, 20));
+    }
+}
@@ -0,0 +1,285 @@
+//! Model-free learning agents. They never read the model directly, the
+//! StateSpace is only used as a simulator: transitions are sampled according to
+//! the probabilities returned by get_future_rewards.
+
+pub mod dyna_q;
+pub mod environment;
+pub mod monte_carlo;
+pub mod q_learning;
+pub mod rollout;
+pub mod sarsa;
+pub mod td_lambda;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::markov_decision_process::{
+    Action,
+    Policy,
+    QTable,
+    State,
+    StateSpace
+};
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+pub enum LearningRate {
+    CONSTANT(f64),
+    INVERSE_VISITS, // 1 / n(s, a)
+    POLYNOMIAL(f64) // 1 / n(s, a)^w, w in (0.5, 1] satisfies the Robbins-Monro conditions
+}
+
+impl LearningRate {
+    pub fn alpha(&self, visits:usize) -> f64 {
+        let n:f64 = visits.max(1) as f64;
+        match self {
+            LearningRate::CONSTANT(alpha) => *alpha,
+            LearningRate::INVERSE_VISITS => 1. / n,
+            LearningRate::POLYNOMIAL(w) => 1. / n.powf(*w),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentConfig {
+    pub gamma: f64,
+    pub learning_rate: LearningRate,
+    pub epsilon: f64, // exploration rate of the epsilon-greedy behaviour policy
+    pub epsilon_decay: f64, // epsilon is multiplied by this after every episode
+    pub min_epsilon: f64,
+    pub episodes: usize,
+    pub max_steps: usize, // per episode
+    pub start_states: Vec<State>, // empty means a uniformly random non-terminal state
+    pub seed: u64,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        AgentConfig {
+            gamma: 0.9,
+            learning_rate: LearningRate::CONSTANT(0.1),
+            epsilon: 0.1,
+            epsilon_decay: 1.0,
+            min_epsilon: 0.0,
+            episodes: 10_000,
+            max_steps: 1_000,
+            start_states: Vec::new(),
+            seed: 42,
+        }
+    }
+}
+
+impl AgentConfig {
+    pub fn epsilon_at(&self, episode:usize) -> f64 {
+        (self.epsilon * self.epsilon_decay.powi(episode as i32)).max(self.min_epsilon)
+    }
+}
+
+/// Samples (next state, reward) for taking a at s, or None if the action leads nowhere.
+pub fn sample_transition<S:StateSpace, R:Rng>(state_space:&S, s:&State, a:&Action, rng:&mut R) -> Option<(State, f64)> {
+    let transitions:Vec<(State, f64, f64)> = state_space.get_future_rewards(s, a);
+    let mut u:f64 = rng.gen::<f64>() * transitions.iter().map(|(_, p, _)| p).sum::<f64>();
+    for (next, p, r) in &transitions {
+        if u < *p {
+            return Some((*next, *r))
+        }
+        u -= p;
+    }
+    // round-off, fall back to the last transition with positive probability
+    transitions.into_iter().rev().find(|(_, p, _)| *p > 0.).map(|(next, _, r)| (next, r))
+}
+
+/// Picks the start state of an episode according to the config.
+pub fn sample_start<S:StateSpace, R:Rng>(state_space:&S, config:&AgentConfig, rng:&mut R) -> State {
+    sample_start_from(state_space, &config.start_states, rng)
+}
+
+/// Uniformly among start_states, or among the non-terminal states if it is empty.
+pub fn sample_start_from<S:StateSpace, R:Rng>(state_space:&S, start_states:&[State], rng:&mut R) -> State {
+    if start_states.is_empty() {
+        let candidates:Vec<&State> = state_space.get_all_states()
+            .filter(|s| !state_space.is_terminal_state(s))
+            .collect();
+        *candidates[rng.gen_range(0..candidates.len())]
+    } else {
+        start_states[rng.gen_range(0..start_states.len())]
+    }
+}
+
+/// Tabular action values, aligned with get_actions_at_state at every state.
+pub struct TabularQ {
+    actions: Vec<Vec<Action>>,
+    q: Vec<Vec<f64>>,
+    visits: Vec<Vec<usize>>,
+    default_action: Action,
+}
+
+impl TabularQ {
+    pub fn new<S:StateSpace>(state_space:&S, default_action:Action) -> Self {
+        let mut actions:Vec<Vec<Action>> = vec![Vec::new(); state_space.len()];
+        for s in state_space.get_all_states() {
+            if !state_space.is_terminal_state(s) {
+                actions[*s] = state_space.get_actions_at_state(s);
+            }
+        }
+        let q:Vec<Vec<f64>> = actions.iter().map(|a| vec![0.; a.len()]).collect();
+        let visits:Vec<Vec<usize>> = actions.iter().map(|a| vec![0; a.len()]).collect();
+        TabularQ { actions, q, visits, default_action }
+    }
+
+    pub fn default_action(&self) -> Action {
+        self.default_action
+    }
+
+    /// Terminal states and states without actions end an episode.
+    pub fn is_absorbing(&self, s:&State) -> bool {
+        self.actions[*s].is_empty()
+    }
+
+    pub fn actions(&self, s:&State) -> &[Action] {
+        &self.actions[*s]
+    }
+
+    pub fn values(&self, s:&State) -> &[f64] {
+        &self.q[*s]
+    }
+
+    /// Index of the greedy action, first one wins ties.
+    pub fn greedy(&self, s:&State) -> usize {
+        self.q[*s].iter().enumerate()
+            .fold((0, f64::MIN), |acc, (i, v)| if *v > acc.1 { (i, *v) } else { acc })
+            .0
+    }
+
+    pub fn max_q(&self, s:&State) -> f64 {
+        if self.is_absorbing(s) {
+            0.
+        } else {
+            self.q[*s][self.greedy(s)]
+        }
+    }
+
+    /// Expected Q(s, .) under the epsilon-greedy policy, 0 at absorbing states.
+    pub fn expected_q(&self, s:&State, epsilon:f64) -> f64 {
+        if self.is_absorbing(s) {
+            return 0.
+        }
+        let q:&[f64] = &self.q[*s];
+        let uniform:f64 = q.iter().sum::<f64>() / q.len() as f64;
+        (1. - epsilon) * q[self.greedy(s)] + epsilon * uniform
+    }
+
+    /// Index of an epsilon-greedy action.
+    pub fn epsilon_greedy<R:Rng>(&self, s:&State, epsilon:f64, rng:&mut R) -> usize {
+        if rng.gen::<f64>() < epsilon {
+            rng.gen_range(0..self.actions[*s].len())
+        } else {
+            self.greedy(s)
+        }
+    }
+
+    /// Moves Q(s, actions[i]) towards target with the given schedule.
+    pub fn update(&mut self, s:&State, i:usize, target:f64, learning_rate:&LearningRate) {
+        self.visits[*s][i] += 1;
+        let alpha:f64 = learning_rate.alpha(self.visits[*s][i]);
+        self.q[*s][i] += alpha * (target - self.q[*s][i]);
+    }
+
+    pub fn policy(&self) -> Policy {
+        (0..self.q.len())
+        .map(|s| {
+            if self.is_absorbing(&s) {
+                self.default_action
+            } else {
+                self.actions[s][self.greedy(&s)]
+            }
+        }).collect()
+    }
+
+    /// max_a Q(s, a), comparable to MarkovDecisionProcess::get_learned_values.
+    pub fn state_values(&self) -> Vec<f64> {
+        (0..self.q.len()).map(|s| self.max_q(&s)).collect()
+    }
+
+    pub fn q_table(&self) -> QTable {
+        self.actions.iter().zip(&self.q)
+        .map(|(actions, q)| actions.iter().copied().zip(q.iter().copied()).collect())
+        .collect()
+    }
+}
+
+/// The part every tabular control agent shares: the simulated model, the config,
+/// the action values of the last learn() and the return of its episodes.
+pub struct AgentCore<S: StateSpace> {
+    state_space: S,
+    config: AgentConfig,
+    q: TabularQ,
+    episode_returns: Vec<f64>,
+}
+
+impl <S: StateSpace> AgentCore<S> {
+
+    pub fn new(state_space:S, default_action:Action, config:AgentConfig) -> Self {
+        let q = TabularQ::new(&state_space, default_action);
+        AgentCore {
+            state_space,
+            config,
+            q,
+            episode_returns: Vec::new()
+        }
+    }
+
+    /// The seeded random number generator and the empty action values every learn() starts from.
+    pub(crate) fn start(&self) -> (StdRng, TabularQ) {
+        (StdRng::seed_from_u64(self.config.seed), TabularQ::new(&self.state_space, self.q.default_action()))
+    }
+
+    /// Keeps the outcome of learn() and returns its greedy policy.
+    pub(crate) fn finish(&mut self, q:TabularQ, episode_returns:Vec<f64>) -> Policy {
+        self.q = q;
+        self.episode_returns = episode_returns;
+        self.q.policy()
+    }
+}
+
+pub trait Agent {
+    type Space: StateSpace;
+
+    fn core(&self) -> &AgentCore<Self::Space>;
+
+    /// Learns for config.episodes episodes from scratch and returns the greedy policy.
+    fn learn(&mut self) -> Policy;
+
+    fn get_policy(&self) -> Policy {
+        self.core().q.policy()
+    }
+
+    fn get_learned_values(&self) -> Vec<f64> {
+        self.core().q.state_values()
+    }
+
+    fn get_q_table(&self) -> QTable {
+        self.core().q.q_table()
+    }
+
+    fn get_episode_returns(&self) -> &[f64] {
+        &self.core().episode_returns
+    }
+
+    fn get_state_space(&self) -> &Self::Space {
+        &self.core().state_space
+    }
+}
+
+/// Runs an episode-based learner and keeps the return (undiscounted sum of
+/// rewards) of every episode.
+pub(crate) fn run_episodes<S, F>(state_space:&S, config:&AgentConfig, rng:&mut StdRng, mut episode:F) -> Vec<f64>
+where
+    S: StateSpace,
+    F: FnMut(State, f64, &mut StdRng) -> f64
+{
+    (0..config.episodes)
+    .map(|i| {
+        let start:State = sample_start(state_space, config, rng);
+        episode(start, config.epsilon_at(i), rng)
+    }).collect()
+}
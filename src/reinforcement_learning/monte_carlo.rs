@@ -0,0 +1,202 @@
+//! Monte Carlo prediction and control. Whole episodes are generated until an
+//! absorbing state (or config.max_steps) and values are the sample averages of
+//! the observed returns, so config.learning_rate is not used here.
+
+use rand::{rngs::StdRng, Rng};
+use std::{collections::HashSet, hash::Hash};
+
+use crate::markov_decision_process::{
+    Action,
+    Policy,
+    State,
+    StateSpace
+};
+use super::{
+    run_episodes,
+    sample_transition,
+    Agent,
+    AgentConfig,
+    AgentCore,
+    LearningRate,
+    TabularQ
+};
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visit {
+    FIRST, // only the first occurrence of a state (or state-action pair) per episode counts
+    EVERY
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum McExploration {
+    EXPLORING_STARTS, // random first action, greedy afterwards
+    EPSILON_SOFT // epsilon-greedy throughout, with config.epsilon
+}
+
+pub struct MonteCarlo<S: StateSpace> {
+    core: AgentCore<S>,
+    visit: Visit,
+    exploration: McExploration,
+}
+
+impl <S: StateSpace> MonteCarlo<S> {
+
+    pub fn new(state_space:S, default_action:Action, config:AgentConfig, visit:Visit, exploration:McExploration) -> MonteCarlo<S> {
+        MonteCarlo {
+            core: AgentCore::new(state_space, default_action, config),
+            visit,
+            exploration
+        }
+    }
+
+    /// Generates one episode as (s, index of a in get_actions_at_state, r) steps,
+    /// choosing actions with `choose`. The episode ends early if it returns None.
+    fn episode<F>(&self, q:&TabularQ, start:State, rng:&mut StdRng, mut choose:F) -> Vec<(State, usize, f64)>
+    where F: FnMut(&State, usize, &mut StdRng) -> Option<usize>
+    {
+        let mut steps:Vec<(State, usize, f64)> = Vec::new();
+        let mut s:State = start;
+        for t in 0..self.core.config.max_steps {
+            if q.is_absorbing(&s) {
+                break
+            }
+            let i:usize = match choose(&s, t, rng) {
+                Some(i) => i,
+                None => break,
+            };
+            let a:Action = q.actions(&s)[i];
+            let (next, r) = match sample_transition(&self.core.state_space, &s, &a, rng) {
+                Some(t) => t,
+                None => break,
+            };
+            steps.push((s, i, r));
+            s = next;
+        }
+        steps
+    }
+
+    /// Discounted returns G_t of every step of an episode, paired with whether
+    /// the step should be averaged under the visit rule.
+    fn returns<K:Eq + Hash>(&self, steps:&[(State, usize, f64)], key:impl Fn(&(State, usize, f64)) -> K) -> Vec<(f64, bool)> {
+        let mut g:f64 = 0.;
+        let mut out:Vec<(f64, bool)> = steps.iter().rev()
+            .map(|(_, _, r)| {
+                g = r + self.core.config.gamma * g;
+                (g, true)
+            }).collect();
+        out.reverse();
+        if self.visit == Visit::FIRST {
+            let mut seen:HashSet<K> = HashSet::new();
+            for (step, counted) in steps.iter().zip(out.iter_mut()) {
+                counted.1 = seen.insert(key(step));
+            }
+        }
+        out
+    }
+
+    /// Monte Carlo prediction of the state values of a fixed policy, comparable to
+    /// MarkovDecisionProcess::get_learned_values. Absorbing and unvisited states are 0.
+    /// As in rollout, an episode ends if pi picks an action that is not available.
+    pub fn evaluate(&self, pi:&Policy) -> Vec<f64> {
+        let (mut rng, q) = self.core.start();
+        let mut values:Vec<f64> = vec![0.; self.core.state_space.len()];
+        let mut visits:Vec<usize> = vec![0; self.core.state_space.len()];
+        run_episodes(&self.core.state_space, &self.core.config, &mut rng, |start, _, rng| {
+            let steps = self.episode(&q, start, rng, |s, _, _| {
+                q.actions(s).iter().position(|a| *a == pi[*s])
+            });
+            let returns = self.returns(&steps, |(s, _, _)| *s);
+            for ((s, _, _), (g, counted)) in steps.iter().zip(&returns) {
+                if *counted {
+                    visits[*s] += 1;
+                    values[*s] += (g - values[*s]) / visits[*s] as f64;
+                }
+            }
+            steps.iter().map(|(_, _, r)| r).sum()
+        });
+        values
+    }
+}
+
+impl <S: StateSpace> Agent for MonteCarlo<S> {
+    type Space = S;
+
+    fn core(&self) -> &AgentCore<S> {
+        &self.core
+    }
+
+    // Monte Carlo control
+    fn learn(&mut self) -> Policy {
+        let (mut rng, mut q) = self.core.start();
+        let exploration = self.exploration;
+        let episode_returns = run_episodes(&self.core.state_space, &self.core.config, &mut rng, |start, epsilon, rng| {
+            let steps = self.episode(&q, start, rng, |s, t, rng| {
+                match exploration {
+                    McExploration::EXPLORING_STARTS if t == 0 => Some(rng.gen_range(0..q.actions(s).len())),
+                    McExploration::EXPLORING_STARTS => Some(q.greedy(s)),
+                    McExploration::EPSILON_SOFT => Some(q.epsilon_greedy(s, epsilon, rng)),
+                }
+            });
+            let returns = self.returns(&steps, |(s, i, _)| (*s, *i));
+            for ((s, i, _), (g, counted)) in steps.iter().zip(&returns) {
+                if *counted {
+                    q.update(s, *i, *g, &LearningRate::INVERSE_VISITS);
+                }
+            }
+            steps.iter().map(|(_, _, r)| r).sum()
+        });
+        self.core.finish(q, episode_returns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::dien::DieN,
+        markov_decision_process::{MarkovDecisionProcess, Solution, TabularMdp}
+    };
+
+    fn die_n_config() -> AgentConfig {
+        AgentConfig { gamma: 1.0, episodes: 20_000, ..AgentConfig::default() }
+    }
+
+    fn assert_close(learned:&[f64], expected:&[f64], tol:f64) {
+        assert!(learned.iter().zip(expected).all(|(l, e)| (l - e).abs() < tol), "{:?} vs {:?}", learned, expected);
+    }
+
+    #[test]
+    fn evaluate_matches_the_planned_values() {
+        let mut mdp = MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 1.0);
+        let solution:Solution = mdp.value_iteration(1e-9).unwrap();
+        for visit in [Visit::FIRST, Visit::EVERY] {
+            let agent = MonteCarlo::new(DieN::new(vec![1,1,1,0,0,0]), 0, die_n_config(), visit, McExploration::EPSILON_SOFT);
+            assert_close(&agent.evaluate(&solution.policy), &solution.values, 0.1);
+        }
+    }
+
+    #[test]
+    fn evaluate_ends_episodes_on_unavailable_actions() {
+        // only action 0 is available at state 0, pi asks for action 3
+        let mdp = TabularMdp::builder(2)
+            .add_transition(0, 0, 1, 1., 5.)
+            .mark_terminal(1)
+            .build();
+        let config = AgentConfig { start_states: vec![0], episodes: 10, ..AgentConfig::default() };
+        let agent = MonteCarlo::new(mdp, 0, config, Visit::FIRST, McExploration::EPSILON_SOFT);
+        assert_eq!(agent.evaluate(&vec![0, 0]), vec![5., 0.]);
+        assert_eq!(agent.evaluate(&vec![3, 0]), vec![0., 0.]);
+    }
+
+    #[test]
+    fn exploring_starts_learns_the_optimal_policy() {
+        let mut mdp = MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 1.0);
+        let solution:Solution = mdp.value_iteration(1e-9).unwrap();
+        let mut agent = MonteCarlo::new(DieN::new(vec![1,1,1,0,0,0]), 0, die_n_config(), Visit::FIRST, McExploration::EXPLORING_STARTS);
+        assert_eq!(agent.learn(), solution.policy);
+        // half of the exploring starts quit right away, so the returns are noisier
+        assert_close(&agent.get_learned_values(), &solution.values, 0.2);
+    }
+}
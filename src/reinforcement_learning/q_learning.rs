@@ -0,0 +1,101 @@
+//! Tabular Q-learning (off-policy TD control). The behaviour policy is
+//! epsilon-greedy while the update bootstraps from the greedy action:
+//! Q(s, a) += alpha * (r + gamma * max_b Q(s', b) - Q(s, a)).
+
+use crate::markov_decision_process::{
+    Action,
+    Policy,
+    State,
+    StateSpace
+};
+use super::{
+    run_episodes,
+    sample_transition,
+    Agent,
+    AgentConfig,
+    AgentCore
+};
+
+pub struct QLearning<S: StateSpace> {
+    core: AgentCore<S>,
+}
+
+impl <S: StateSpace> QLearning<S> {
+
+    pub fn new(state_space:S, default_action:Action, config:AgentConfig) -> QLearning<S> {
+        QLearning { core: AgentCore::new(state_space, default_action, config) }
+    }
+}
+
+impl <S: StateSpace> Agent for QLearning<S> {
+    type Space = S;
+
+    fn core(&self) -> &AgentCore<S> {
+        &self.core
+    }
+
+    fn learn(&mut self) -> Policy {
+        let (mut rng, mut q) = self.core.start();
+        let state_space = &self.core.state_space;
+        let config = &self.core.config;
+        let episode_returns = run_episodes(state_space, config, &mut rng, |start, epsilon, rng| {
+            let mut s:State = start;
+            let mut episode_return:f64 = 0.;
+            for _ in 0..config.max_steps {
+                if q.is_absorbing(&s) {
+                    break
+                }
+                let i:usize = q.epsilon_greedy(&s, epsilon, rng);
+                let a:Action = q.actions(&s)[i];
+                let (next, r) = match sample_transition(state_space, &s, &a, rng) {
+                    Some(t) => t,
+                    None => break,
+                };
+                let target:f64 = r + config.gamma * q.max_q(&next);
+                q.update(&s, i, target, &config.learning_rate);
+                episode_return += r;
+                s = next;
+            }
+            episode_return
+        });
+        self.core.finish(q, episode_returns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::{dien::DieN, grid_world::GridWorld},
+        markov_decision_process::{MarkovDecisionProcess, Solution},
+        reinforcement_learning::LearningRate
+    };
+
+    #[test]
+    fn learns_the_optimal_grid_world_policy() {
+        let mut mdp = MarkovDecisionProcess::new(GridWorld::default(), 0, 0.9);
+        let solution:Solution = mdp.value_iteration(1e-9).unwrap();
+        for learning_rate in [LearningRate::CONSTANT(0.01), LearningRate::POLYNOMIAL(0.6)] {
+            let config = AgentConfig { episodes: 50_000, learning_rate, seed: 7, ..AgentConfig::default() };
+            let mut agent = QLearning::new(GridWorld::default(), 0, config);
+            assert_eq!(agent.learn(), solution.policy);
+        }
+    }
+
+    #[test]
+    fn learns_the_optimal_die_n_policy() {
+        let mut mdp = MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 1.0);
+        let solution:Solution = mdp.value_iteration(1e-9).unwrap();
+        let config = AgentConfig {
+            gamma: 1.0,
+            learning_rate: LearningRate::INVERSE_VISITS,
+            episodes: 20_000,
+            ..AgentConfig::default()
+        };
+        let mut agent = QLearning::new(DieN::new(vec![1,1,1,0,0,0]), 0, config);
+        assert_eq!(agent.learn(), solution.policy);
+        let values:Vec<f64> = agent.get_learned_values();
+        assert!(values.iter().zip(&solution.values).all(|(q, v)| (q - v).abs() < 0.1),
+            "{:?} vs {:?}", values, solution.values);
+    }
+}
@@ -0,0 +1,155 @@
+//! Monte Carlo evaluation of a fixed policy. Episodes are simulated through an
+//! Environment, so the start states, the step limit and the seed are those of the
+//! environment, e.g. a Simulator. The mean discounted return of the episodes that
+//! started in s estimates v_pi(s), which can be checked against the values a planner
+//! returned. With several start states the pooled mean estimates a mixture of those
+//! values, so use for_start or by_start to compare them one by one.
+
+use std::collections::BTreeMap;
+
+use crate::markov_decision_process::{
+    Policy,
+    State
+};
+use super::environment::Environment;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Episode {
+    pub start: State,
+    pub discounted_return: f64,
+    pub length: usize,
+    pub final_state: State,
+}
+
+#[derive(Debug, Clone)]
+pub struct RolloutStats {
+    pub runs: Vec<Episode>, // every episode, in the order they were run
+    pub returns: Vec<f64>, // discounted return of every episode
+    pub mean: f64,
+    pub variance: f64, // sample variance, 0 for a single episode
+    pub lengths: BTreeMap<usize, usize>, // episode length -> number of episodes
+    // State every episode ended in -> number of episodes. Episodes cut off by the
+    // step limit of the environment count with the state they were cut off in.
+    pub final_states: BTreeMap<State, usize>,
+}
+
+impl RolloutStats {
+
+    pub fn new(episodes:Vec<Episode>) -> Self {
+        assert!(!episodes.is_empty(), "At least one episode is needed.");
+        let returns:Vec<f64> = episodes.iter().map(|e| e.discounted_return).collect();
+        let mut lengths:BTreeMap<usize, usize> = BTreeMap::new();
+        let mut final_states:BTreeMap<State, usize> = BTreeMap::new();
+        for e in &episodes {
+            *lengths.entry(e.length).or_insert(0) += 1;
+            *final_states.entry(e.final_state).or_insert(0) += 1;
+        }
+        let n:f64 = returns.len() as f64;
+        let mean:f64 = returns.iter().sum::<f64>() / n;
+        let variance:f64 = if returns.len() > 1 {
+            returns.iter().map(|g| (g - mean).powi(2)).sum::<f64>() / (n - 1.)
+        } else {
+            0.
+        };
+        RolloutStats { runs: episodes, returns, mean, variance, lengths, final_states }
+    }
+
+    pub fn episodes(&self) -> usize {
+        self.returns.len()
+    }
+
+    /// The statistics of the episodes that started in s, None if there were none.
+    pub fn for_start(&self, s:&State) -> Option<RolloutStats> {
+        let episodes:Vec<Episode> = self.runs.iter().filter(|e| e.start == *s).copied().collect();
+        if episodes.is_empty() {
+            None
+        } else {
+            Some(RolloutStats::new(episodes))
+        }
+    }
+
+    /// The statistics of every start state that was drawn.
+    pub fn by_start(&self) -> BTreeMap<State, RolloutStats> {
+        let mut groups:BTreeMap<State, Vec<Episode>> = BTreeMap::new();
+        for e in &self.runs {
+            groups.entry(e.start).or_default().push(*e);
+        }
+        groups.into_iter().map(|(s, episodes)| (s, RolloutStats::new(episodes))).collect()
+    }
+
+    pub fn std_error(&self) -> f64 {
+        (self.variance / self.episodes() as f64).sqrt()
+    }
+
+    /// Normal approximation mean +- z * std_error, z = 1.96 for 95%.
+    pub fn confidence_interval(&self, z:f64) -> (f64, f64) {
+        let half:f64 = z * self.std_error();
+        (self.mean - half, self.mean + half)
+    }
+
+    pub fn mean_length(&self) -> f64 {
+        let total:usize = self.lengths.iter().map(|(len, count)| len * count).sum();
+        total as f64 / self.episodes() as f64
+    }
+
+    /// Fraction of the episodes that ended in s.
+    pub fn final_state_frequency(&self, s:&State) -> f64 {
+        self.final_states.get(s).copied().unwrap_or(0) as f64 / self.episodes() as f64
+    }
+}
+
+/// Runs the given number of episodes of pi in env, each from env.reset(). An episode
+/// also ends if pi picks an action that is not available.
+pub fn rollout<E:Environment>(env:&mut E, pi:&Policy, episodes:usize, gamma:f64) -> RolloutStats {
+    assert!(episodes > 0, "At least one episode is needed.");
+    let mut records:Vec<Episode> = Vec::with_capacity(episodes);
+    for _ in 0..episodes {
+        let start:State = env.reset();
+        let mut s:State = start;
+        let mut g:f64 = 0.;
+        let mut discount:f64 = 1.;
+        let mut length:usize = 0;
+        while env.actions().contains(&pi[s]) {
+            let (next, r, done) = env.step(pi[s]);
+            g += discount * r;
+            discount *= gamma;
+            length += 1;
+            s = next;
+            if done {
+                break
+            }
+        }
+        records.push(Episode { start, discounted_return: g, length, final_state: s });
+    }
+    RolloutStats::new(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::dien::DieN,
+        markov_decision_process::{MarkovDecisionProcess, MDPSolver, Solution},
+        reinforcement_learning::environment::Simulator
+    };
+
+    #[test]
+    fn per_start_means_match_the_planned_values() {
+        let mut mdp = MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 1.0);
+        let solution:Solution = mdp.solve(MDPSolver::VALUE_ITER, 1e-9).unwrap();
+        let starts:Vec<State> = vec![0, 2, 5];
+        let mut env = Simulator::new(DieN::new(vec![1,1,1,0,0,0]), starts.clone(), 1_000, 7);
+        let stats:RolloutStats = rollout(&mut env, &solution.policy, 30_000, 1.0);
+
+        let by_start:BTreeMap<State, RolloutStats> = stats.by_start();
+        assert_eq!(by_start.keys().copied().collect::<Vec<State>>(), starts);
+        assert_eq!(by_start.values().map(|s| s.episodes()).sum::<usize>(), stats.episodes());
+        for (s, per_start) in &by_start {
+            assert!(per_start.runs.iter().all(|e| e.start == *s));
+            assert!((per_start.mean - solution.values[*s]).abs() <= 4. * per_start.std_error() + 1e-9,
+                "start {}: simulated {} vs planned {}", s, per_start.mean, solution.values[*s]);
+        }
+        assert_eq!(stats.for_start(&2).unwrap().returns, by_start[&2].returns);
+        assert!(stats.for_start(&1).is_none());
+    }
+}
@@ -0,0 +1,123 @@
+//! On-policy TD control. SARSA bootstraps from the action the epsilon-greedy
+//! policy actually takes next, Expected SARSA from the expectation over that
+//! policy's actions, which removes the sampling variance of the next action.
+
+use crate::markov_decision_process::{
+    Action,
+    Policy,
+    State,
+    StateSpace
+};
+use super::{
+    run_episodes,
+    sample_transition,
+    Agent,
+    AgentConfig,
+    AgentCore
+};
+
+pub struct Sarsa<S: StateSpace> {
+    core: AgentCore<S>,
+    expected: bool,
+}
+
+impl <S: StateSpace> Sarsa<S> {
+
+    pub fn new(state_space:S, default_action:Action, config:AgentConfig) -> Sarsa<S> {
+        Sarsa {
+            core: AgentCore::new(state_space, default_action, config),
+            expected: false
+        }
+    }
+
+    pub fn new_expected(state_space:S, default_action:Action, config:AgentConfig) -> Sarsa<S> {
+        let mut agent = Sarsa::new(state_space, default_action, config);
+        agent.expected = true;
+        agent
+    }
+}
+
+impl <S: StateSpace> Agent for Sarsa<S> {
+    type Space = S;
+
+    fn core(&self) -> &AgentCore<S> {
+        &self.core
+    }
+
+    fn learn(&mut self) -> Policy {
+        let (mut rng, mut q) = self.core.start();
+        let state_space = &self.core.state_space;
+        let config = &self.core.config;
+        let expected = self.expected;
+        let episode_returns = run_episodes(state_space, config, &mut rng, |start, epsilon, rng| {
+            let mut s:State = start;
+            let mut episode_return:f64 = 0.;
+            if q.is_absorbing(&s) {
+                return episode_return
+            }
+            let mut i:usize = q.epsilon_greedy(&s, epsilon, rng);
+            for _ in 0..config.max_steps {
+                let a:Action = q.actions(&s)[i];
+                let (next, r) = match sample_transition(state_space, &s, &a, rng) {
+                    Some(t) => t,
+                    None => break,
+                };
+                episode_return += r;
+                if q.is_absorbing(&next) {
+                    q.update(&s, i, r, &config.learning_rate);
+                    break
+                }
+                let next_i:usize = q.epsilon_greedy(&next, epsilon, rng);
+                let bootstrap:f64 = if expected {
+                    q.expected_q(&next, epsilon)
+                } else {
+                    q.values(&next)[next_i]
+                };
+                q.update(&s, i, r + config.gamma * bootstrap, &config.learning_rate);
+                s = next;
+                i = next_i;
+            }
+            episode_return
+        });
+        self.core.finish(q, episode_returns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::cliff_walking::CliffWalking,
+        reinforcement_learning::{q_learning::QLearning, LearningRate}
+    };
+
+    fn last_100_mean(returns:&[f64]) -> f64 {
+        returns[returns.len() - 100..].iter().sum::<f64>() / 100.
+    }
+
+    #[test]
+    fn on_policy_agents_walk_further_from_the_cliff() {
+        // Sutton & Barto, Example 6.6: under epsilon-greedy exploration Q-learning
+        // learns the path along the edge and keeps falling off, SARSA the safer path.
+        let config = AgentConfig {
+            gamma: 1.0,
+            learning_rate: LearningRate::CONSTANT(0.5),
+            epsilon: 0.1,
+            episodes: 500,
+            start_states: vec![CliffWalking::default().start()],
+            ..AgentConfig::default()
+        };
+        let mut sarsa = Sarsa::new(CliffWalking::default(), 1, config.clone());
+        let mut expected = Sarsa::new_expected(CliffWalking::default(), 1, config.clone());
+        let mut q_learning = QLearning::new(CliffWalking::default(), 1, config);
+        sarsa.learn();
+        expected.learn();
+        q_learning.learn();
+        let (s, e, q) = (
+            last_100_mean(sarsa.get_episode_returns()),
+            last_100_mean(expected.get_episode_returns()),
+            last_100_mean(q_learning.get_episode_returns())
+        );
+        assert!(s > q && e > q, "SARSA {}, Expected SARSA {}, Q-learning {}", s, e, q);
+    }
+}
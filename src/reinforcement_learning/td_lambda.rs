@@ -0,0 +1,127 @@
+//! TD(0) and TD(lambda) evaluation of a fixed policy from sampled experience.
+//! Accumulating and replacing traces use the classic backward view, dutch
+//! traces use true online TD(lambda) (van Seijen & Sutton, 2014), which for
+//! tabular values matches the forward view lambda-return exactly.
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::markov_decision_process::{
+    Policy,
+    State,
+    StateSpace
+};
+use super::{
+    run_episodes,
+    sample_transition,
+    AgentConfig
+};
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trace {
+    ACCUMULATING, // e(s) += 1
+    REPLACING, // e(s) = 1
+    DUTCH // e(s) += 1 - alpha * gamma * lambda * e(s)
+}
+
+/// TD(0), i.e. TD(lambda) with lambda = 0.
+pub fn td_zero<S:StateSpace>(state_space:&S, pi:&Policy, config:&AgentConfig) -> Vec<f64> {
+    td_lambda(state_space, pi, config, 0., Trace::ACCUMULATING)
+}
+
+/// Estimates the state values of pi over config.episodes episodes, comparable to
+/// MarkovDecisionProcess::get_learned_values. The step size at s is taken from
+/// config.learning_rate with the number of visits to s. Terminal states are 0.
+pub fn td_lambda<S:StateSpace>(state_space:&S, pi:&Policy, config:&AgentConfig, lambda:f64, trace:Trace) -> Vec<f64> {
+    let n:usize = state_space.len();
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut values:Vec<f64> = vec![0.; n];
+    let mut visits:Vec<usize> = vec![0; n];
+    let mut e:Vec<f64> = vec![0.; n];
+    let decay:f64 = config.gamma * lambda;
+
+    run_episodes(state_space, config, &mut rng, |start, _, rng| {
+        e.iter_mut().for_each(|x| *x = 0.);
+        let mut s:State = start;
+        let mut v_old:f64 = 0.; // only used by true online TD(lambda)
+        let mut episode_return:f64 = 0.;
+        for _ in 0..config.max_steps {
+            if state_space.is_terminal_state(&s) {
+                break
+            }
+            let (next, r) = match sample_transition(state_space, &s, &pi[s], rng) {
+                Some(t) => t,
+                None => break,
+            };
+            episode_return += r;
+            visits[s] += 1;
+            let alpha:f64 = config.learning_rate.alpha(visits[s]);
+            let v:f64 = values[s];
+            let v_next:f64 = if state_space.is_terminal_state(&next) { 0. } else { values[next] };
+            let delta:f64 = r + config.gamma * v_next - v;
+
+            let e_s:f64 = e[s];
+            e.iter_mut().for_each(|x| *x *= decay);
+            match trace {
+                Trace::ACCUMULATING => e[s] += 1.,
+                Trace::REPLACING => e[s] = 1.,
+                Trace::DUTCH => e[s] += 1. - alpha * decay * e_s,
+            }
+
+            if trace == Trace::DUTCH {
+                values.iter_mut().zip(&e).for_each(|(value, x)| *value += alpha * (delta + v - v_old) * x);
+                values[s] -= alpha * (v - v_old);
+                v_old = v_next;
+            } else {
+                values.iter_mut().zip(&e).for_each(|(value, x)| *value += alpha * delta * x);
+            }
+            s = next;
+        }
+        episode_return
+    });
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::dien::DieN,
+        markov_decision_process::MarkovDecisionProcess,
+        reinforcement_learning::LearningRate
+    };
+
+    fn die_n_setup() -> (DieN, Policy, Vec<f64>, AgentConfig) {
+        let mut mdp = MarkovDecisionProcess::new(DieN::new(vec![1,1,1,0,0,0]), 0, 1.0);
+        let pi:Policy = mdp.value_iteration(1e-9).unwrap().policy;
+        let config = AgentConfig {
+            gamma: 1.0,
+            learning_rate: LearningRate::INVERSE_VISITS,
+            episodes: 20_000,
+            ..AgentConfig::default()
+        };
+        (DieN::new(vec![1,1,1,0,0,0]), pi, mdp.get_learned_values(), config)
+    }
+
+    fn assert_close(learned:&[f64], expected:&[f64], tol:f64) {
+        assert!(learned.iter().zip(expected).all(|(l, e)| (l - e).abs() < tol), "{:?} vs {:?}", learned, expected);
+    }
+
+    #[test]
+    fn estimates_match_the_planned_values() {
+        let (die, pi, expected, config) = die_n_setup();
+        assert_close(&td_zero(&die, &pi, &config), &expected, 0.1);
+        for trace in [Trace::ACCUMULATING, Trace::REPLACING, Trace::DUTCH] {
+            for lambda in [0.5, 1.] {
+                assert_close(&td_lambda(&die, &pi, &config, lambda, trace), &expected, 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn dutch_traces_reduce_to_td_zero() {
+        let (die, pi, _, config) = die_n_setup();
+        let dutch:Vec<f64> = td_lambda(&die, &pi, &config, 0., Trace::DUTCH);
+        assert_close(&dutch, &td_zero(&die, &pi, &config), 1e-9);
+    }
+}